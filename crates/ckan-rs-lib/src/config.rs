@@ -9,6 +9,38 @@ pub struct CkanRsConfig {
 	data_dir: std::path::PathBuf,
 	https_only: bool,
 	do_checksums: bool,
+	#[serde(default = "default_repositories")]
+	repositories: Vec<crate::metadb::Repository>,
+	#[serde(default)]
+	metadb_compression: crate::metadb::Compression,
+	#[serde(default = "default_download_limit", deserialize_with = "deserialize_download_limit")]
+	download_limit: usize,
+	#[serde(default = "default_download_limit_per_host", deserialize_with = "deserialize_download_limit")]
+	download_limit_per_host: usize,
+	#[serde(default)]
+	download_cache_limit: Option<u64>,
+}
+
+fn default_repositories() -> Vec<crate::metadb::Repository> {
+	vec![Default::default()]
+}
+
+fn default_download_limit() -> usize {
+	8
+}
+
+fn default_download_limit_per_host() -> usize {
+	4
+}
+
+/// Raises a limit of zero to one, no download would ever start with a limit of zero.
+fn deserialize_download_limit<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+	use serde::Deserialize;
+	let limit = usize::deserialize(deserializer)?;
+	if limit == 0 {
+		log::warn!("Download limit of 0 in config, using 1 instead.");
+	}
+	Ok(limit.max(1))
 }
 
 impl Default for CkanRsConfig {
@@ -46,6 +78,11 @@ impl Default for CkanRsConfig {
 			},
 			https_only: true,
 			do_checksums: true,
+			repositories: default_repositories(),
+			metadb_compression: Default::default(),
+			download_limit: default_download_limit(),
+			download_limit_per_host: default_download_limit_per_host(),
+			download_cache_limit: None,
 		}
 	}
 }
@@ -91,6 +128,67 @@ impl CkanRsConfig {
 		self.do_checksums = do_checksums;
 	}
 
+	/// The repositories used to generate the [`MetaDB`](crate::MetaDB).
+	pub fn repositories(&self) -> &Vec<crate::metadb::Repository> {
+		&self.repositories
+	}
+	/// Repository names must be unique, returns if the list was valid and set or not.
+	pub fn set_repositories(&mut self, repositories: Vec<crate::metadb::Repository>) -> bool {
+		let names = repositories.iter().map(|r| &r.name).collect::<std::collections::HashSet<_>>();
+		if names.len() == repositories.len() {
+			self.repositories = repositories;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Compression used when saving the [`MetaDB`](crate::MetaDB).
+	pub fn metadb_compression(&self) -> crate::metadb::Compression {
+		self.metadb_compression
+	}
+	pub fn set_metadb_compression(&mut self, metadb_compression: crate::metadb::Compression) {
+		self.metadb_compression = metadb_compression;
+	}
+
+	/// The maximum number of packages downloaded at once.
+	pub fn download_limit(&self) -> usize {
+		self.download_limit
+	}
+	/// Returns if the limit was above zero and was set or not.
+	pub fn set_download_limit(&mut self, download_limit: usize) -> bool {
+		if download_limit > 0 {
+			self.download_limit = download_limit;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// The maximum number of packages downloaded at once from a single host.
+	pub fn download_limit_per_host(&self) -> usize {
+		self.download_limit_per_host
+	}
+	/// Returns if the limit was above zero and was set or not.
+	pub fn set_download_limit_per_host(&mut self, download_limit_per_host: usize) -> bool {
+		if download_limit_per_host > 0 {
+			self.download_limit_per_host = download_limit_per_host;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// The maximum size in bytes of the download cache, `None` for no limit.
+	/// 
+	/// See [`evict_download_cache()`](crate::installation::cache::evict_download_cache()).
+	pub fn download_cache_limit(&self) -> Option<u64> {
+		self.download_cache_limit
+	}
+	pub fn set_download_cache_limit(&mut self, download_cache_limit: Option<u64>) {
+		self.download_cache_limit = download_cache_limit;
+	}
+
 	/// Loads the config file from a file.
 	/// 
 	/// # Platform Specific
@@ -142,4 +240,18 @@ fn get_config_path() -> std::path::PathBuf {
 	};
 
 	path.join("CKAN-rs").join("config.json")
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn zero_download_limits_are_raised() {
+		let mut json = serde_json::to_value(CkanRsConfig::default()).unwrap();
+		json["download_limit"] = serde_json::json!(0);
+		json["download_limit_per_host"] = serde_json::json!(0);
+		let config = serde_json::from_value::<CkanRsConfig>(json).unwrap();
+		assert_eq!(config.download_limit(), 1);
+		assert_eq!(config.download_limit_per_host(), 1);
+	}
+}
@@ -14,8 +14,12 @@ pub enum Error {
 	SerdeJSON(#[from] serde_json::Error),
 	#[error("bincode error: {0}")]
 	Bincode(#[from] bincode::Error),
+	#[error("zip error: {0}")]
+	Zip(#[from] zip::result::ZipError),
 	#[error("parsing error: {0}")]
 	Parse(String),
+	#[error("needs regeneration: {0}")]
+	NeedsRegeneration(String),
 	#[error("validation error: {0}")]
 	Validation(String),
 	#[error("selection invalid")]
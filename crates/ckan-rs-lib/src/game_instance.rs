@@ -1,6 +1,6 @@
 //! Game installation handling.
 
-use std::{io::{Read, Write}, path::Path, collections::HashSet};
+use std::{io::Read, path::Path, collections::{HashMap, HashSet}};
 
 use crate::metadb::package;
 use crate::metadb::package::KspVersionReal;
@@ -12,7 +12,7 @@ pub mod filetracker;
 
 /// A single instance of a game.
 /// 
-/// This struct is saved to a JSON file in the ckan-rs data directory.
+/// This struct is saved to a file in the ckan-rs data directory, see [`FORMAT_VERSION`].
 /// 
 /// Instances are named so they can be loaded by that name.
 /// 
@@ -26,23 +26,86 @@ pub struct GameInstance {
 	path: std::path::PathBuf,
 	compatible_ksp_versions: Vec<KspVersionReal>,
 	package_tree: PackageTree<Complete>,
+	/// Packages added from outside the MetaDB, see [`add_local_package()`](GameInstance::add_local_package()).
+	#[serde(with = "local_package_json")]
+	local_packages: Vec<package::Package>,
+	/// Archives given for local packages, these are copied into the download cache instead of downloading the package.
+	local_archives: HashMap<package::PackageIdentifier, std::path::PathBuf>,
 	pub tracked: filetracker::TrackedFiles,
 	pub deployment_dir: std::path::PathBuf,
 }
 
+/// The version of the instance file format written by this library.
+///
+/// Files start with a magic number followed by the format version as a little endian `u32`, the instance follows.
+/// Files written before the format was versioned have no magic number and are converted when loaded.
+///
+/// Bumped whenever the layout of [`GameInstance`] changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 8] = b"CKANRSGI";
+
+/// Local packages are saved as their `.ckan` JSON so instance files don't depend on the layout of [`Package`](package::Package).
+mod local_package_json {
+	use crate::metadb::package::Package;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	pub fn serialize<S: Serializer>(packages: &[Package], serializer: S) -> Result<S::Ok, S::Error> {
+		packages.iter()
+			.map(|p| p.write_to_json().to_string())
+			.collect::<Vec<_>>()
+			.serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Package>, D::Error> {
+		Vec::<String>::deserialize(deserializer)?.iter()
+			.map(|json| {
+				let json = serde_json::from_str(json).map_err(serde::de::Error::custom)?;
+				Package::read_from_json(json).map_err(serde::de::Error::custom)
+			})
+			.collect()
+	}
+}
+
+/// Layout of instance files written before the format was versioned, these have no local packages.
+#[derive(serde::Deserialize)]
+struct GameInstanceV0 {
+	name: String,
+	path: std::path::PathBuf,
+	compatible_ksp_versions: Vec<KspVersionReal>,
+	package_tree: PackageTree<Complete>,
+	tracked: filetracker::TrackedFiles,
+	deployment_dir: std::path::PathBuf,
+}
+
+impl From<GameInstanceV0> for GameInstance {
+	fn from(v0: GameInstanceV0) -> Self {
+		GameInstance {
+			name: v0.name,
+			path: v0.path,
+			compatible_ksp_versions: v0.compatible_ksp_versions,
+			package_tree: v0.package_tree,
+			local_packages: Vec::new(),
+			local_archives: HashMap::new(),
+			tracked: v0.tracked,
+			deployment_dir: v0.deployment_dir,
+		}
+	}
+}
+
 impl GameInstance {
 	/// Creates a new instance.
 	/// 
 	/// # Parameters
-	/// - `builds` - A list of KSP build numbers and their corisponding version string. Usually sourced from [`get_game_builds()`](crate::metadb::MetaDB::get_game_builds())
+	/// - `builds` - A catalog of KSP build numbers and their corisponding versions. Either [loaded on its own](crate::metadb::BuildCatalog::load_from_disk()) or sourced from [`get_game_builds()`](crate::metadb::MetaDB::get_game_builds())
 	/// - `name` - The identifier CKAN-rs will use to track this instance.
 	/// - `game_root_directory` - The path to the root of the game install. this is where the KSP executable is located.
 	/// - `deployment_dir` - This is where modded files will be installed to before being linked to the games directory.
-	/// due to the use of hard links this directory must be on the same drive as `game_root_directory`.
+	///   due to the use of hard links this directory must be on the same drive as `game_root_directory`.
 	/// # Errors
 	/// - [`IO`](crate::error::Error::IO) when the directory is invalid.
 	/// - [`Parse`](crate::error::Error::Parse) when extracting the build id from `buildID.txt`.
-	pub fn new(config: &crate::CkanRsConfig, builds: &crate::metadb::BuildIDList, name: String, game_root_directory: impl AsRef<std::path::Path>, deployment_dir: std::path::PathBuf) -> crate::Result<GameInstance> {
+	pub fn new(config: &crate::CkanRsConfig, builds: &crate::metadb::BuildCatalog, name: String, game_root_directory: impl AsRef<std::path::Path>, deployment_dir: std::path::PathBuf) -> crate::Result<GameInstance> {
 		let instances_dir = config.data_dir().join("instances");
 		if !instances_dir.exists() {
 			std::fs::create_dir_all(&instances_dir)?;
@@ -82,8 +145,8 @@ impl GameInstance {
 			
 			let id = id.ok_or_else(|| crate::Error::Parse("Build ID not found in buildID.txt".to_string()))?;
 			
-			if let Some(s) = builds.get(&id) {
-				vec![KspVersionReal::try_from(s.as_str()).expect("builds.json ksp version string should be valid.")]
+			if let Some(version) = builds.get_version(id) {
+				vec![version.clone()]
 			} else {
 				return Err(crate::Error::Parse(format!("builds.json missing build id {}, try updating metadb.", id)))
 			}
@@ -99,6 +162,8 @@ impl GameInstance {
 			compatible_ksp_versions,
 			tracked: Default::default(),
 			package_tree,
+			local_packages: Vec::new(),
+			local_archives: HashMap::new(),
 			deployment_dir
 		})
 	}
@@ -123,9 +188,14 @@ impl GameInstance {
 		self.package_tree.get_all_packages()
 	}
 
+	/// Resolves the packages required after adding and removing targets, enabling them on success.
+	///
+	/// The instance's local packages are [registered](GameInstance::register_local_packages()) with `metadb` first
+	/// so they can be resolved even if `metadb` was loaded after they were added.
+	#[allow(clippy::result_unit_err)]
 	pub fn alter_package_requirements<F>(
 		&mut self,
-		metadb: &crate::MetaDB,
+		metadb: &mut crate::MetaDB,
 		add: impl IntoIterator<Item = crate::relationship_resolver::InstallTarget>,
 		remove: impl IntoIterator<Item = crate::relationship_resolver::InstallTarget>,
 		decision_handler: F) 
@@ -133,6 +203,8 @@ impl GameInstance {
 	where F: Fn(&mut PackageTree<InProgress>, Vec<crate::relationship_resolver::DecisionInfo>),
 	{
 		use crate::relationship_resolver::ResolverStatus;
+		self.put_local_packages(metadb);
+		let metadb = &*metadb;
 		let mut pt = self.package_tree.clone().alter_package_requirements(add, remove);
 		loop {
 			match pt.attempt_resolve(metadb) {
@@ -164,6 +236,107 @@ impl GameInstance {
 		}
 	}
 
+	/* Local Packages */
+
+	/// Adds a package which isn't in any repository, such as an unpublished mod.
+	///
+	/// The package is added to `metadb` so it takes part in resolution, download and extraction like any other package.
+	/// The instance remembers the package, resolving and redeploying find it in any MetaDB used later.
+	/// Adding a package with the same identifier and version as a local package replaces it.
+	///
+	/// # Parameters
+	/// - `archive` - A zip file containing the package's content. It is copied into the download cache so the package's `download` isn't used,
+	///   unless the download is forced.
+	///
+	/// # Errors
+	/// - [`AlreadyExists`](crate::error::Error::AlreadyExists) when a package from a repository has the same identifier and version.
+	/// - [`IO`](crate::error::Error::IO) when `archive` doesn't exist or can't be copied.
+	pub fn add_local_package(&mut self, config: &crate::CkanRsConfig, metadb: &mut crate::MetaDB, package: package::Package, archive: Option<&Path>) -> crate::Result<()> {
+		let archive = archive.map(|a| a.canonicalize()).transpose()?;
+
+		log::info!("Adding local package {} to instance {}", package.identifier, self.name);
+		metadb.put_local_package(package.clone())?;
+		match archive {
+			Some(archive) => self.local_archives.insert(package.identifier.clone(), archive),
+			None => self.local_archives.remove(&package.identifier),
+		};
+		self.cache_local_archive(config, &package.identifier, true)?;
+		self.local_packages.retain(|p| p.identifier != package.identifier);
+		self.local_packages.push(package);
+		Ok(())
+	}
+
+	/// Reads a `.ckan` file and adds it with [`add_local_package()`](GameInstance::add_local_package()).
+	///
+	/// # Errors
+	/// - [`IO`](crate::error::Error::IO) when reading the file.
+	/// - [`SerdeJSON`](crate::error::Error::SerdeJSON) or [`Parse`](crate::error::Error::Parse) when the file isn't a valid package.
+	/// - Any error from [`add_local_package()`](GameInstance::add_local_package()).
+	pub fn add_local_package_from_file(&mut self, config: &crate::CkanRsConfig, metadb: &mut crate::MetaDB, path: impl AsRef<Path>, archive: Option<&Path>) -> crate::Result<()> {
+		let json = serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))?;
+		let package = package::Package::read_from_json(json)?;
+		self.add_local_package(config, metadb, package, archive)
+	}
+
+	/// Forgets a local package, removing it from `metadb` as well.
+	///
+	/// The package must be disabled separately if it is enabled.
+	pub fn remove_local_package(&mut self, metadb: &mut crate::MetaDB, id: &package::PackageIdentifier) -> Option<package::Package> {
+		let index = self.local_packages.iter().position(|p| &p.identifier == id)?;
+		metadb.take_local_package(id);
+		self.local_archives.remove(id);
+		Some(self.local_packages.remove(index))
+	}
+
+	pub fn local_packages(&self) -> &Vec<package::Package> {
+		&self.local_packages
+	}
+
+	/// Gets a local package of the instance.
+	pub fn get_local_package(&self, id: &package::PackageIdentifier) -> Option<&package::Package> {
+		self.local_packages.iter().find(|p| &p.identifier == id)
+	}
+
+	/// Adds the instance's local packages to `metadb` and copies their archives back into the download cache if they are missing.
+	///
+	/// [`alter_package_requirements()`](GameInstance::alter_package_requirements()) and [`redeploy_packages()`](GameInstance::redeploy_packages())
+	/// find local packages on their own, this is needed to download local packages using a MetaDB loaded after they were added.
+	///
+	/// Local packages which have since been published to a repository are skipped in favour of the repository's package.
+	pub fn register_local_packages(&self, config: &crate::CkanRsConfig, metadb: &mut crate::MetaDB) {
+		for id in self.put_local_packages(metadb) {
+			if let Err(e) = self.cache_local_archive(config, id, false) {
+				log::warn!("Couldn't copy the archive of local package {}: {}", id, e);
+			}
+		}
+	}
+
+	/// Adds the instance's local packages to `metadb`, returning the ones which were added.
+	fn put_local_packages(&self, metadb: &mut crate::MetaDB) -> Vec<&package::PackageIdentifier> {
+		let mut added = Vec::new();
+		for package in &self.local_packages {
+			if metadb.put_local_package(package.clone()).is_err() {
+				log::warn!("Local package {} is also in a repository, using the repository's package.", package.identifier);
+				continue;
+			}
+			added.push(&package.identifier);
+		}
+		added
+	}
+
+	/// Copies the archive given for a local package to its download path, existing downloads are only replaced if `replace` is set.
+	fn cache_local_archive(&self, config: &crate::CkanRsConfig, id: &package::PackageIdentifier, replace: bool) -> crate::Result<()> {
+		let Some(archive) = self.local_archives.get(id) else {
+			return Ok(())
+		};
+		let download_path = crate::installation::download::get_package_download_path(config, id);
+		if replace || !download_path.exists() {
+			std::fs::create_dir_all(download_path.with_file_name(""))?;
+			std::fs::copy(archive, download_path)?;
+		}
+		Ok(())
+	}
+
 	/// Disables all packages so they are not deployed the next time [`redeploy_packages()`](GameInstance::redeploy_packages()) is called.
 	pub fn clear_enabled_packages(&mut self) {
 		log::trace!("Clearing enabled packages on instance at {}", self.game_dir().display());
@@ -176,32 +349,192 @@ impl GameInstance {
 	/// 
 	/// # Errors
 	/// - [`IO`](crate::error::Error::IO) when opening or reading from the file.
-	/// - [`SerdeJSON`](crate::error::Error::SerdeJSON) when deserializing the file.
+	/// - [`Parse`](crate::error::Error::Parse) when the file uses a newer format.
+	/// - [`Bincode`](crate::error::Error::Bincode) when deserializing the file.
 	pub fn load_by_name(config: &crate::CkanRsConfig, name: impl AsRef<str>) -> crate::Result<Self> {
 		let path = config.data_dir().join("instances").join(format!("{}.json", name.as_ref()));
 		Self::load_by_file(path)
 	}
 
+	/// Loads every saved instance.
+	/// 
+	/// # Errors
+	/// - [`IO`](crate::error::Error::IO) when reading the instances directory or an instance file.
+	/// - [`Parse`](crate::error::Error::Parse) when an instance file uses a newer format.
+	/// - [`Bincode`](crate::error::Error::Bincode) when deserializing an instance file.
+	pub fn load_all(config: &crate::CkanRsConfig) -> crate::Result<Vec<Self>> {
+		let dir = config.data_dir().join("instances");
+		if !dir.exists() {
+			return Ok(Vec::new());
+		}
+		let mut instances = Vec::new();
+		for entry in std::fs::read_dir(dir)? {
+			let path = entry?.path();
+			if path.extension().is_some_and(|e| e == "json") {
+				instances.push(Self::load_by_file(path)?);
+			}
+		}
+		Ok(instances)
+	}
+
 	/// Loads an instance from a file at a given path.
 	/// 
+	/// Files written before the format was versioned are converted to the current format.
+	/// 
 	/// # Errors
 	/// - [`IO`](crate::error::Error::IO) when opening or reading from the file.
-	/// - [`SerdeJSON`](crate::error::Error::SerdeJSON) when deserializing the file.
+	/// - [`Parse`](crate::error::Error::Parse) when the file uses a newer format.
+	/// - [`Bincode`](crate::error::Error::Bincode) when deserializing the file.
 	fn load_by_file(path: impl AsRef<Path>) -> crate::Result<Self> {
-		let file = std::fs::File::open(path)?;
-		Ok(bincode::deserialize_from(file)?)
+		let data = std::fs::read(path)?;
+		let Some(versioned) = data.strip_prefix(MAGIC) else {
+			log::info!("Upgrading unversioned instance file to format version {}", FORMAT_VERSION);
+			return Ok(bincode::deserialize::<GameInstanceV0>(&data)?.into())
+		};
+
+		let (version, body) = versioned.split_at_checked(4).ok_or_else(|| crate::Error::Parse("instance file is truncated".to_string()))?;
+		let version = u32::from_le_bytes(version.try_into().expect("version should be 4 bytes."));
+		if version != FORMAT_VERSION {
+			return Err(crate::Error::Parse(format!("instance format version {} is not supported, the current version is {}", version, FORMAT_VERSION)))
+		}
+		Ok(bincode::deserialize(body)?)
 	}
 
-	/// Saves the instance to a JSON file.
+	/// Saves the instance using the current [format](FORMAT_VERSION).
 	/// 
 	/// # Errors
 	/// - [`IO`](crate::error::Error::IO) when opening the file, writing to it or creating it's parent directories.
 	/// - [`Bincode`](crate::error::Error::Bincode) when serializing the file.
 	pub fn save_to_disk(&self, config: &crate::CkanRsConfig) -> crate::Result<()> {
+		use std::io::Write;
+
 		let path = config.data_dir().join("instances").join(format!("{}.json", self.name));
 		std::fs::create_dir_all(path.with_file_name(""))?;
-		let file = std::fs::File::create(path)?;
-		bincode::serialize_into(file, self)?;
+		let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+		file.write_all(MAGIC)?;
+		file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+		bincode::serialize_into(&mut file, self)?;
+		file.flush()?;
 		Ok(())
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn local_packages_are_remembered() {
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::create_dir_all(dir.path().join("data")).unwrap();
+		std::fs::create_dir_all(dir.path().join("downloads")).unwrap();
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_data_dir(dir.path().join("data")));
+		assert!(config.set_download_dir(dir.path().join("downloads")));
+
+		let mut builds = crate::metadb::BuildCatalog::default();
+		builds.insert(3173, KspVersionReal::new("1.12.3").unwrap());
+		let game_dir = ckan_rs_test_utils::create_fake_game_instance().unwrap();
+		let mut instance = GameInstance::new(&config, &builds, "local".into(), &game_dir, dir.path().join("deployment")).unwrap();
+
+		let archive = dir.path().join("Internal.zip");
+		std::fs::write(&archive, b"not really a zip").unwrap();
+		let ckan = dir.path().join("Internal.ckan");
+		std::fs::write(&ckan, serde_json::json!({
+			"spec_version": "v1.26",
+			"identifier": "Internal",
+			"version": "1.0",
+			"name": "Internal",
+			"abstract": "An unpublished mod.",
+			"author": "tester",
+			"license": "MIT",
+			"download": "https://example.com/Internal.zip",
+			"download_hash": { "sha256": package::DownloadHash::compute(package::HashAlgorithm::Sha256, b"not really a zip").to_hex() },
+		}).to_string()).unwrap();
+
+		let mut db = crate::MetaDB::default();
+		instance.add_local_package_from_file(&config, &mut db, &ckan, Some(&archive)).unwrap();
+		let package = db.get_latest("Internal").unwrap();
+		assert_eq!(package.download.as_deref(), Some("https://example.com/Internal.zip"));
+
+		/* The archive is used instead of downloading */
+		let results = crate::installation::download::download_packages_content(&config, &[package], false).await;
+		let downloaded = results[0].1.as_ref().unwrap();
+		assert_eq!(std::fs::read(downloaded).unwrap(), b"not really a zip");
+
+		instance.save_to_disk(&config).unwrap();
+		std::fs::remove_file(downloaded).unwrap();
+		let instance = GameInstance::load_by_name(&config, "local").unwrap();
+		let mut db = crate::MetaDB::default();
+		instance.register_local_packages(&config, &mut db);
+		assert!(db.get_latest("Internal").is_some());
+		assert_eq!(std::fs::read(downloaded).unwrap(), b"not really a zip");
+	}
+
+	#[tokio::test]
+	async fn local_packages_are_resolved_and_deployed_without_registering() {
+		use std::io::Write;
+
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::create_dir_all(dir.path().join("data")).unwrap();
+		std::fs::create_dir_all(dir.path().join("downloads")).unwrap();
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_data_dir(dir.path().join("data")));
+		assert!(config.set_download_dir(dir.path().join("downloads")));
+
+		let mut builds = crate::metadb::BuildCatalog::default();
+		builds.insert(3173, KspVersionReal::new("1.12.3").unwrap());
+		let game_dir = ckan_rs_test_utils::create_fake_game_instance().unwrap();
+		let mut instance = GameInstance::new(&config, &builds, "local".into(), &game_dir, dir.path().join("deployment")).unwrap();
+
+		let archive = dir.path().join("Internal.zip");
+		{
+			let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+			zip.start_file("Internal/Internal.cfg", Default::default()).unwrap();
+			zip.write_all(b"content").unwrap();
+			zip.finish().unwrap();
+		}
+		let package = crate::test_fixtures::package("Internal", "1.0", serde_json::json!({ "download_content_type": "application/zip" }));
+		instance.add_local_package(&config, &mut crate::MetaDB::default(), package, Some(&archive)).unwrap();
+		instance.save_to_disk(&config).unwrap();
+
+		/* Neither MetaDB has had the local packages registered */
+		let mut instance = GameInstance::load_by_name(&config, "local").unwrap();
+		let target = crate::relationship_resolver::InstallTarget { identifier: "Internal".to_string(), ..Default::default() };
+		let (added, _) = instance.alter_package_requirements(&mut crate::MetaDB::default(), vec![target], vec![], |_, _| {}).unwrap();
+		assert_eq!(added, vec![crate::test_fixtures::id("Internal", "1.0")]);
+
+		let package = instance.get_local_package(&added[0]).unwrap();
+		crate::installation::content::extract_content_to_deployment(&config, &instance, package).unwrap();
+		instance.redeploy_packages(&crate::MetaDB::default()).await.unwrap();
+		assert_eq!(std::fs::read(game_dir.join("GameData/Internal/Internal.cfg")).unwrap(), b"content");
+	}
+
+	#[test]
+	fn unversioned_instances_are_upgraded() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_data_dir(dir.path().to_path_buf()));
+
+		let mut builds = crate::metadb::BuildCatalog::default();
+		builds.insert(3173, KspVersionReal::new("1.12.3").unwrap());
+		let game_dir = ckan_rs_test_utils::create_fake_game_instance().unwrap();
+		let instance = GameInstance::new(&config, &builds, "old".into(), &game_dir, dir.path().join("deployment")).unwrap();
+
+		/* Instances were saved without a header or local packages */
+		let old = bincode::serialize(&(&instance.name, &instance.path, &instance.compatible_ksp_versions, &instance.package_tree, &instance.tracked, &instance.deployment_dir)).unwrap();
+		let path = dir.path().join("instances").join("old.json");
+		std::fs::write(&path, old).unwrap();
+		let loaded = GameInstance::load_by_name(&config, "old").unwrap();
+		assert_eq!(loaded.game_dir(), instance.game_dir());
+		assert!(loaded.local_packages().is_empty());
+
+		loaded.save_to_disk(&config).unwrap();
+		assert!(std::fs::read(&path).unwrap().starts_with(MAGIC));
+		GameInstance::load_by_name(&config, "old").unwrap();
+
+		let mut newer = MAGIC.to_vec();
+		newer.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+		std::fs::write(&path, newer).unwrap();
+		assert!(matches!(GameInstance::load_by_name(&config, "old"), Err(crate::Error::Parse(_))));
+	}
+}
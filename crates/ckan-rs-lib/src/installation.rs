@@ -7,4 +7,22 @@ are `application/zip` so we're just going to consider them all zips and error ot
 
 pub mod download;
 pub mod content;
-pub mod deployment;
\ No newline at end of file
+pub mod deployment;
+pub mod cache;
+use crate::metadb::package::PackageIdentifier;
+
+/// Progress of the installation steps, sent by the `_with_progress` variants of
+/// [`download_packages_content()`](download::download_packages_content()),
+/// [`extract_content_to_deployment()`](content::extract_content_to_deployment()) and
+/// [`redeploy_packages()`](crate::game_instance::GameInstance::redeploy_packages()).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallProgress {
+	/// Bytes of a package's download received so far, `total` is `None` when the server doesn't report the size.
+	Downloaded { package: PackageIdentifier, bytes: u64, total: Option<u64> },
+	/// Files of a package's archive extracted so far.
+	Extracted { package: PackageIdentifier, files: usize, total: usize },
+	/// Files of a package linked into the game directory so far.
+	Deployed { package: PackageIdentifier, files: usize, total: usize },
+}
+
+type ProgressSender = tokio::sync::mpsc::UnboundedSender<InstallProgress>;
@@ -0,0 +1,348 @@
+//! Operations on the download cache as a whole.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::metadb::package::{Package, PackageIdentifier};
+use super::download::{self, DownloadError};
+
+/// An archive in the download cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedDownload {
+	pub path: PathBuf,
+	/// The package the archive belongs to, `None` if it isn't in the MetaDB.
+	pub package: Option<PackageIdentifier>,
+	/// Size in bytes.
+	pub size: u64,
+	/// When the archive was last downloaded or read from the cache.
+	pub last_used: SystemTime,
+}
+
+/// Result of [`verify_download_cache()`].
+#[derive(Debug, Default)]
+pub struct CacheVerification {
+	/// Archives which match their package.
+	pub valid: Vec<PathBuf>,
+	/// Archives which don't match their package and why.
+	pub invalid: Vec<(PathBuf, DownloadError)>,
+	/// Archives which don't belong to any package in the MetaDB.
+	pub unknown: Vec<PathBuf>,
+}
+
+/// Maps the file names of cached archives to the packages they belong to.
+fn get_cached_packages<'db>(config: &crate::CkanRsConfig, db: &'db crate::MetaDB) -> HashMap<std::ffi::OsString, &'db Package> {
+	db.get_packages().iter()
+		.filter_map(|p| download::get_package_download_path(config, &p.identifier).file_name().map(|f| (f.to_owned(), p)))
+		.collect()
+}
+
+/// Records that a cached archive has been used so it is evicted after less recently used archives.
+pub(super) fn mark_used(path: &Path) {
+	let result = std::fs::File::options()
+		.write(true)
+		.open(path)
+		.and_then(|f| f.set_modified(SystemTime::now()));
+	if let Err(e) = result {
+		log::warn!("Failed to update last used time of {}: {}", path.display(), e);
+	}
+}
+
+/// Reads the archives in the download directory, partial downloads are skipped.
+/// 
+/// Returns tuples of the archive's path, size and last used time, least recently used first.
+fn read_archives(config: &crate::CkanRsConfig) -> crate::Result<Vec<(PathBuf, u64, SystemTime)>> {
+	let mut archives = Vec::new();
+	if !config.download_dir().exists() {
+		return Ok(archives);
+	}
+
+	for entry in std::fs::read_dir(config.download_dir())? {
+		let entry = entry?;
+		let path = entry.path();
+		let metadata = entry.metadata()?;
+		if metadata.is_file() && path.extension().is_some_and(|e| e == "zip") {
+			archives.push((path, metadata.len(), metadata.modified()?));
+		}
+	}
+	archives.sort_by_key(|(_, _, last_used)| *last_used);
+
+	Ok(archives)
+}
+
+/// Paths of the archives needed by the enabled packages of any saved [`GameInstance`](crate::game_instance::GameInstance).
+fn get_protected_archives(config: &crate::CkanRsConfig) -> crate::Result<HashSet<PathBuf>> {
+	Ok(crate::game_instance::GameInstance::load_all(config)?.iter()
+		.flat_map(|instance| instance.enabled_packages())
+		.map(|id| download::get_package_download_path(config, &id))
+		.collect())
+}
+
+/// Lists the archives in the download cache, least recently used first.
+/// 
+/// # Errors
+/// - [`IO`](crate::Error::IO) when reading the download directory.
+pub fn list_download_cache(config: &crate::CkanRsConfig, db: &crate::MetaDB) -> crate::Result<Vec<CachedDownload>> {
+	let packages = get_cached_packages(config, db);
+	Ok(read_archives(config)?.into_iter()
+		.map(|(path, size, last_used)| CachedDownload {
+			package: path.file_name().and_then(|f| packages.get(f)).map(|p| p.identifier.clone()),
+			path,
+			size,
+			last_used,
+		})
+		.collect())
+}
+
+/// Removes the least recently used archives until the cache is within [`download_cache_limit()`](crate::CkanRsConfig::download_cache_limit()).
+/// 
+/// Archives needed by the enabled packages of any saved instance are never evicted,
+/// so the cache may remain over the limit. Partial downloads don't count towards the limit.
+/// 
+/// Downloading doesn't apply the limit itself, call this after [saving](crate::game_instance::GameInstance::save_to_disk())
+/// the instances the downloads were for so their archives are protected.
+/// 
+/// Returns the paths of the removed archives.
+/// 
+/// # Errors
+/// - [`IO`](crate::Error::IO) when reading the download directory or removing an archive.
+/// - [`Bincode`](crate::Error::Bincode) when reading a saved instance.
+pub fn evict_download_cache(config: &crate::CkanRsConfig) -> crate::Result<Vec<PathBuf>> {
+	let Some(limit) = config.download_cache_limit() else {
+		return Ok(Vec::new())
+	};
+
+	let archives = read_archives(config)?;
+	let mut size = archives.iter().map(|(_, size, _)| size).sum::<u64>();
+	if size <= limit {
+		return Ok(Vec::new())
+	}
+
+	let protected = get_protected_archives(config)?;
+	let mut removed = Vec::new();
+	for (path, archive_size, _) in archives {
+		if size <= limit {
+			break;
+		}
+		if protected.contains(&path) {
+			continue;
+		}
+		log::info!("Evicting {} from the download cache.", path.display());
+		std::fs::remove_file(&path)?;
+		size -= archive_size;
+		removed.push(path);
+	}
+
+	if size > limit {
+		log::warn!("Download cache is {} bytes over its limit with only archives needed by instances remaining.", size - limit);
+	}
+	Ok(removed)
+}
+
+/// Removes a package's archive and any partial download of it from the cache.
+/// 
+/// Returns `true` if anything was removed.
+/// 
+/// # Errors
+/// - [`IO`](crate::Error::IO) when removing a file.
+pub fn remove_cached_download(config: &crate::CkanRsConfig, id: &PackageIdentifier) -> crate::Result<bool> {
+	let mut removed = false;
+	for path in [download::get_package_download_path(config, id), download::get_package_partial_download_path(config, id)] {
+		match std::fs::remove_file(path) {
+			Ok(()) => removed = true,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+			Err(e) => return Err(e.into()),
+		}
+	}
+	Ok(removed)
+}
+
+/// Removes every archive and partial download from the cache.
+/// 
+/// Returns the paths of the removed files.
+/// 
+/// # Errors
+/// - [`IO`](crate::Error::IO) when reading the download directory or removing a file.
+pub fn purge_download_cache(config: &crate::CkanRsConfig) -> crate::Result<Vec<PathBuf>> {
+	let mut removed = Vec::new();
+	if !config.download_dir().exists() {
+		return Ok(removed);
+	}
+
+	for entry in std::fs::read_dir(config.download_dir())? {
+		let path = entry?.path();
+		if path.is_file() && path.extension().is_some_and(|e| e == "zip" || e == "part") {
+			std::fs::remove_file(&path)?;
+			removed.push(path);
+		}
+	}
+	Ok(removed)
+}
+
+/// Removes every archive which isn't needed by the enabled packages of a saved instance.
+/// 
+/// Returns the paths of the removed archives.
+/// 
+/// # Errors
+/// - [`IO`](crate::Error::IO) when reading the download directory or removing an archive.
+/// - [`Bincode`](crate::Error::Bincode) when reading a saved instance.
+pub fn purge_unused_downloads(config: &crate::CkanRsConfig) -> crate::Result<Vec<PathBuf>> {
+	let protected = get_protected_archives(config)?;
+	let mut removed = Vec::new();
+	for (path, _, _) in read_archives(config)? {
+		if !protected.contains(&path) {
+			std::fs::remove_file(&path)?;
+			removed.push(path);
+		}
+	}
+	Ok(removed)
+}
+
+/// Verifies every archive in the download directory against its package in `db`.
+/// 
+/// Partial downloads are skipped. Local packages are only recognised if they have been
+/// [registered](crate::game_instance::GameInstance::register_local_packages()) with `db`.
+/// 
+/// # Parameters
+/// - `remove_invalid` - Delete archives which fail verification so they are downloaded again.
+/// 
+/// # Errors
+/// - [`IO`](crate::Error::IO) when reading the download directory or removing an archive.
+pub async fn verify_download_cache(config: &crate::CkanRsConfig, db: &crate::MetaDB, remove_invalid: bool) -> crate::Result<CacheVerification> {
+	let mut verification = CacheVerification::default();
+	let packages = get_cached_packages(config, db);
+	for (path, _, _) in read_archives(config)? {
+		match path.file_name().and_then(|f| packages.get(f)) {
+			None => verification.unknown.push(path),
+			Some(package) => match download::verify_cached_download(config, package).await {
+				Ok(()) => verification.valid.push(path),
+				Err(e) => {
+					log::warn!("Cached download of package {} is invalid: {}", package.identifier, e);
+					if remove_invalid {
+						tokio::fs::remove_file(&path).await?;
+					}
+					verification.invalid.push((path, e));
+				},
+			},
+		}
+	}
+
+	Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::metadb::package::{DownloadHash, HashAlgorithm};
+	use crate::test_fixtures;
+	use serde_json::json;
+
+	/// A package whose download is `content`.
+	fn package(identifier: &str, content: &[u8]) -> Package {
+		test_fixtures::package(identifier, "1.0", json!({
+			"download_size": content.len(),
+			"download_hash": { "sha1": DownloadHash::compute(HashAlgorithm::Sha1, content).to_hex() },
+		}))
+	}
+
+	#[tokio::test]
+	async fn cache_is_verified() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_download_dir(dir.path().to_path_buf()));
+
+		let mut db = crate::MetaDB::default();
+		let good = package("Good", b"good");
+		let bad = package("Bad", b"bad");
+		std::fs::write(download::get_package_download_path(&config, &good.identifier), b"good").unwrap();
+		std::fs::write(download::get_package_download_path(&config, &bad.identifier), b"BAD").unwrap();
+		std::fs::write(dir.path().join("Unknown1.0.zip"), b"unknown").unwrap();
+		std::fs::write(download::get_package_partial_download_path(&config, &good.identifier), b"go").unwrap();
+		let (good_path, bad_path) = (download::get_package_download_path(&config, &good.identifier), download::get_package_download_path(&config, &bad.identifier));
+		db.put_local_package(good).unwrap();
+		db.put_local_package(bad).unwrap();
+
+		assert!(download::verify_cached_download(&config, db.get_latest("Good").unwrap()).await.is_ok());
+		assert!(matches!(
+			download::verify_cached_download(&config, db.get_latest("Bad").unwrap()).await,
+			Err(DownloadError::DifferentHashes { .. })
+		));
+
+		let verification = verify_download_cache(&config, &db, true).await.unwrap();
+		assert_eq!(verification.valid, vec![good_path]);
+		assert_eq!(verification.invalid.len(), 1);
+		assert_eq!(verification.unknown, vec![dir.path().join("Unknown1.0.zip")]);
+		assert!(!bad_path.exists());
+	}
+
+	/// A config with its own data and download directories in `dir`.
+	fn config(dir: &Path) -> crate::CkanRsConfig {
+		std::fs::create_dir_all(dir.join("data")).unwrap();
+		std::fs::create_dir_all(dir.join("downloads")).unwrap();
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_data_dir(dir.join("data")));
+		assert!(config.set_download_dir(dir.join("downloads")));
+		config
+	}
+
+	/// Caches a 10 byte archive for each of `identifiers`, each used a minute after the last.
+	fn cache_archives(config: &crate::CkanRsConfig, db: &mut crate::MetaDB, identifiers: &[&str]) -> Vec<PathBuf> {
+		let now = SystemTime::now();
+		let mut paths = Vec::new();
+		for (i, identifier) in identifiers.iter().enumerate() {
+			let package = package(identifier, b"0123456789");
+			let path = download::get_package_download_path(config, &package.identifier);
+			std::fs::write(&path, b"0123456789").unwrap();
+			std::fs::File::options().write(true).open(&path).unwrap()
+				.set_modified(now - std::time::Duration::from_secs(60 * (identifiers.len() - i) as u64)).unwrap();
+			db.put_local_package(package).unwrap();
+			paths.push(path);
+		}
+		paths
+	}
+
+	#[test]
+	fn least_recently_used_are_evicted() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut config = config(dir.path());
+		let mut db = crate::MetaDB::default();
+		let paths = cache_archives(&config, &mut db, &["Old", "Middle", "New"]);
+
+		let listed = list_download_cache(&config, &db).unwrap();
+		assert_eq!(listed.iter().map(|c| c.package.as_ref().unwrap().identifier.as_str()).collect::<Vec<_>>(), vec!["Old", "Middle", "New"]);
+		assert!(listed.iter().all(|c| c.size == 10));
+
+		assert!(evict_download_cache(&config).unwrap().is_empty());
+		config.set_download_cache_limit(Some(25));
+		assert_eq!(evict_download_cache(&config).unwrap(), vec![paths[0].clone()]);
+
+		mark_used(&paths[1]);
+		config.set_download_cache_limit(Some(10));
+		assert_eq!(evict_download_cache(&config).unwrap(), vec![paths[2].clone()]);
+		assert!(paths[1].exists());
+
+		assert!(remove_cached_download(&config, &db.get_latest("Middle").unwrap().identifier).unwrap());
+		assert!(purge_download_cache(&config).unwrap().is_empty());
+	}
+
+	#[test]
+	fn enabled_archives_are_not_evicted() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut config = config(dir.path());
+		let mut db = crate::MetaDB::default();
+		let paths = cache_archives(&config, &mut db, &["Old", "Middle", "New"]);
+
+		let mut builds = crate::metadb::BuildCatalog::default();
+		builds.insert(3173, crate::metadb::package::KspVersionReal::new("1.12.3").unwrap());
+		let game_dir = ckan_rs_test_utils::create_fake_game_instance().unwrap();
+		let mut instance = crate::game_instance::GameInstance::new(&config, &builds, "cache".into(), &game_dir, dir.path().join("deployment")).unwrap();
+		let target = crate::relationship_resolver::InstallTarget { identifier: "Old".to_string(), ..Default::default() };
+		instance.alter_package_requirements(&mut db, vec![target], vec![], |_, _| {}).unwrap();
+		instance.save_to_disk(&config).unwrap();
+
+		/* The least recently used archive is skipped */
+		config.set_download_cache_limit(Some(10));
+		assert_eq!(evict_download_cache(&config).unwrap(), vec![paths[1].clone(), paths[2].clone()]);
+		assert!(paths[0].exists());
+		assert!(purge_unused_downloads(&config).unwrap().is_empty());
+	}
+}
@@ -33,20 +33,87 @@ impl crate::game_instance::GameInstance {
 /// - Returns [`ContentError::PackageNotInstallable`] when given a metapackage or dlc which have no installable content.
 /// - Currently only zip is supported and so returns [`ContentError::UnsupportedContentType`] if any other content type is provided.
 pub fn extract_content_to_deployment(config: &crate::CkanRsConfig, instance: &crate::game_instance::GameInstance, package: &crate::metadb::package::Package) -> Result<(), ContentError> {
+	extract_content_to_deployment_inner(config, instance, package, None)
+}
+
+/// Same as [`extract_content_to_deployment()`] but sends [`Extracted`](super::InstallProgress::Extracted) progress to `progress` as files are written.
+pub fn extract_content_to_deployment_with_progress(config: &crate::CkanRsConfig, instance: &crate::game_instance::GameInstance, package: &crate::metadb::package::Package, progress: tokio::sync::mpsc::UnboundedSender<super::InstallProgress>) -> Result<(), ContentError> {
+	extract_content_to_deployment_inner(config, instance, package, Some(progress))
+}
+
+fn extract_content_to_deployment_inner(config: &crate::CkanRsConfig, instance: &crate::game_instance::GameInstance, package: &crate::metadb::package::Package, progress: Option<super::ProgressSender>) -> Result<(), ContentError> {
 	let ct = package.download_content_type.as_ref().ok_or(ContentError::PackageNotInstallable)?;
 	if ct == "application/zip" {
 		let download_path = super::download::get_package_download_path(config, &package.identifier);
 		let deploy_path = instance.get_package_deployment_path(package);
 		let mut zip = zip::ZipArchive::new(
-			std::fs::File::open(download_path)?
+			std::fs::File::open(&download_path)?
 		)?;
+		super::cache::mark_used(&download_path);
 		
 		std::fs::create_dir_all(deploy_path.with_file_name(""))?;
-		match zip.extract(deploy_path) {
-			Ok(_) => Ok(()),
-			Err(_) => todo!(), /* TODO: Clear left over files and return error */
+		let result = extract_zip(&mut zip, &deploy_path, |files, total| {
+			if let Some(progress) = &progress {
+				let _ = progress.send(super::InstallProgress::Extracted { package: package.identifier.clone(), files, total });
+			}
+		});
+		if result.is_err() {
+			/* Don't leave a partial extraction behind to be mistaken for the package's content */
+			let _ = std::fs::remove_dir_all(&deploy_path);
 		}
+		result
 	} else {
 		Err(ContentError::UnsupportedContentType)
 	}
-}
\ No newline at end of file
+}
+
+/// Extracts every entry of `zip` into `destination`, `on_file` is given the files written so far and the total.
+fn extract_zip<R: std::io::Read + std::io::Seek>(zip: &mut zip::ZipArchive<R>, destination: &std::path::Path, on_file: impl Fn(usize, usize)) -> Result<(), ContentError> {
+	let total = zip.file_names().filter(|name| !name.ends_with('/')).count();
+	let mut files = 0;
+	for i in 0..zip.len() {
+		let mut entry = zip.by_index(i)?;
+		let path = destination.join(entry.enclosed_name().ok_or(zip::result::ZipError::InvalidArchive("Invalid file path"))?);
+		if entry.is_dir() {
+			std::fs::create_dir_all(&path)?;
+			continue;
+		}
+		std::fs::create_dir_all(path.with_file_name(""))?;
+		std::io::copy(&mut entry, &mut std::fs::File::create(&path)?)?;
+		#[cfg(unix)]
+		if let Some(mode) = entry.unix_mode() {
+			use std::os::unix::fs::PermissionsExt;
+			std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+		}
+		files += 1;
+		on_file(files, total);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+
+	#[test]
+	fn extraction_reports_files() {
+		let mut buf = std::io::Cursor::new(Vec::new());
+		{
+			let mut writer = zip::ZipWriter::new(&mut buf);
+			writer.add_directory("Example/", Default::default()).unwrap();
+			for name in ["Example/Example.dll", "Example/Parts/part.cfg"] {
+				writer.start_file(name, Default::default()).unwrap();
+				writer.write_all(b"content").unwrap();
+			}
+			writer.finish().unwrap();
+		}
+
+		let dir = tempfile::tempdir().unwrap();
+		let mut zip = zip::ZipArchive::new(buf).unwrap();
+		let reported = std::cell::RefCell::new(Vec::new());
+		extract_zip(&mut zip, dir.path(), |files, total| reported.borrow_mut().push((files, total))).unwrap();
+		assert_eq!(reported.into_inner(), vec![(1, 2), (2, 2)]);
+		assert_eq!(std::fs::read(dir.path().join("Example/Parts/part.cfg")).unwrap(), b"content");
+	}
+}
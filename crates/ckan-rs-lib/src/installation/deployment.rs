@@ -15,40 +15,55 @@ use crate::metadb::package::{Package, PackageIdentifier};
 impl crate::game_instance::GameInstance {
 	/// Cleans the instance of deployed files then link all required package files.
 	/// 
+	/// Packages missing from `db` are looked up in the instance's [local packages](crate::game_instance::GameInstance::local_packages()).
+	/// 
 	/// # Errors
 	/// - [`IO`](DeploymentError::IO) - When removing previously deployed files.
-	/// - [`MissingPackage`](DeploymentError::MissingPackage) - If a package is missing from the MetaDB after being enabled.
+	/// - [`MissingPackage`](DeploymentError::MissingPackage) - If a package is missing from the MetaDB and local packages after being enabled.
 	/// - [`MissingContent`](DeploymentError::MissingContent) - If a package's content has not been extracted before being deployed.
 	pub async fn redeploy_packages(&mut self, db: &crate::MetaDB) -> Result<(), DeploymentError> {
+		self.redeploy_packages_inner(db, None).await
+	}
+
+	/// Same as [`redeploy_packages()`](Self::redeploy_packages()) but sends [`Deployed`](super::InstallProgress::Deployed) progress to `progress` as files are linked.
+	pub async fn redeploy_packages_with_progress(&mut self, db: &crate::MetaDB, progress: tokio::sync::mpsc::UnboundedSender<super::InstallProgress>) -> Result<(), DeploymentError> {
+		self.redeploy_packages_inner(db, Some(progress)).await
+	}
+
+	async fn redeploy_packages_inner(&mut self, db: &crate::MetaDB, progress: Option<super::ProgressSender>) -> Result<(), DeploymentError> {
 		self.clean_deployment().await?;
 		log::trace!("Redeploying packages for instance at {}", self.game_dir().display());
 	
-		let mut tracked_files = Vec::<(&PackageIdentifier, Vec<String>)>::new();
+		let mut tracked_files = Vec::<(PackageIdentifier, Vec<String>)>::new();
 		
 		for package in self.enabled_packages() {
 			log::trace!("Deploying package {}", package);
-			let package = db.get_from_unique_id(package).ok_or(DeploymentError::MissingPackage)?;
+			let package = db.get_from_unique_id(&package).or_else(|| self.get_local_package(&package)).ok_or(DeploymentError::MissingPackage)?;
 			let path = self.get_package_deployment_path(package);
 			let path = path.exists().then_some(path).ok_or(DeploymentError::MissingContent)?;
 	
 			let mut package_files = Vec::<String>::new();
 	
 			let install_instructions = get_install_instructions(package, path)?;
+			let total = install_instructions.len();
 		
 			for (source, destination) in install_instructions {
 				/* TODO: Install Methods */
 				let final_destination = self.game_dir().join(&destination);
-				std::fs::create_dir_all(&final_destination.with_file_name(""))?;
+				std::fs::create_dir_all(final_destination.with_file_name(""))?;
 				std::fs::hard_link(&source, &final_destination)?;
 				package_files.push(destination.to_string_lossy().to_string());
+				if let Some(progress) = &progress {
+					let _ = progress.send(super::InstallProgress::Deployed { package: package.identifier.clone(), files: package_files.len(), total });
+				}
 			}
 	
-			tracked_files.push((&package.identifier, package_files));
+			tracked_files.push((package.identifier.clone(), package_files));
 		}
 	
 		for (package, files) in tracked_files {
 			for f in files {
-				self.tracked.add_file(package, f);
+				self.tracked.add_file(&package, f);
 			}
 		}
 	
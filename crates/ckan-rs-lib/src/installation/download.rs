@@ -10,8 +10,17 @@ pub enum DownloadError {
 	#[error("given package does not have downloadable content.")]
 	PackageMissingDownloadFields,
 	/// The downloaded content hash does not match hash in the package.
-	#[error("downloaded content hash does not match hash in package.")]
-	DifferentHashes,
+	#[error("downloaded content hash {actual} does not match hash in package {expected}.")]
+	DifferentHashes {
+		expected: DownloadHash,
+		actual: DownloadHash,
+	},
+	/// The downloaded content is a different size to the package's `download_size`.
+	#[error("downloaded content is {actual} bytes but package expects {expected} bytes.")]
+	DifferentSize {
+		expected: u64,
+		actual: u64,
+	},
 	#[error("reqwest error: {0}")]
 	Reqwest(#[from] reqwest::Error),
 	#[error("IO error: {0}")]
@@ -22,24 +31,111 @@ pub fn get_package_download_path(config: &crate::CkanRsConfig, id: &crate::metad
 	config.download_dir().join(id.identifier.clone() + &id.version.to_string() + ".zip")
 }
 
+/// Path a package is downloaded to before it has been verified and moved to [`get_package_download_path()`].
+pub fn get_package_partial_download_path(config: &crate::CkanRsConfig, id: &crate::metadb::package::PackageIdentifier) -> std::path::PathBuf {
+	config.download_dir().join(id.identifier.clone() + &id.version.to_string() + ".zip.part")
+}
+
+/// The first byte of a partial response, `None` if its `Content-Range` is missing or malformed.
+fn get_content_range_start(response: &reqwest::Response) -> Option<u64> {
+	response.headers().get(reqwest::header::CONTENT_RANGE)?
+		.to_str().ok()?
+		.strip_prefix("bytes ")?
+		.split('-').next()?
+		.trim().parse().ok()
+}
+
+/// Feeds the content of the file at `path` through `hasher`.
+async fn hash_file(path: &std::path::Path, hasher: &mut DownloadHasher) -> std::io::Result<()> {
+	use tokio::io::AsyncReadExt;
+
+	let mut file = tokio::fs::File::open(path).await?;
+	let mut buf = vec![0; 64 * 1024];
+	loop {
+		let read = file.read(&mut buf).await?;
+		if read == 0 {
+			return Ok(())
+		}
+		hasher.update(&buf[..read]);
+	}
+}
+
+/// Checks a package's cached download against its `download_size` and, when [checksums](crate::CkanRsConfig::get_do_checksums()) are enabled, its hash.
+/// 
+/// # Errors
+/// - [`IO`](DownloadError::IO) when the download can't be read, including when it isn't cached.
+/// - [`DifferentSize`](DownloadError::DifferentSize) when the size doesn't match.
+/// - [`DifferentHashes`](DownloadError::DifferentHashes) when the hash doesn't match.
+pub async fn verify_cached_download(config: &crate::CkanRsConfig, package: &Package) -> Result<(), DownloadError> {
+	let path = get_package_download_path(config, &package.identifier);
+	let actual = tokio::fs::metadata(&path).await?.len();
+	if let Some(expected) = package.download_size {
+		if actual != expected {
+			return Err(DownloadError::DifferentSize { expected, actual });
+		}
+	}
+
+	if let Some(expected) = package.get_download_hash().filter(|_| config.get_do_checksums()) {
+		let mut hasher = DownloadHasher::new(expected.algorithm());
+		hash_file(&path, &mut hasher).await?;
+		let actual = hasher.finish();
+		if &actual != expected {
+			return Err(DownloadError::DifferentHashes { expected: expected.clone(), actual });
+		}
+	}
+
+	Ok(())
+}
+
 /// Downloads multiple package's contents.
 /// 
+/// Packages are downloaded concurrently up to [`download_limit()`](crate::CkanRsConfig::download_limit()) at once
+/// and [`download_limit_per_host()`](crate::CkanRsConfig::download_limit_per_host()) from any one host.
+/// 
+/// Content is streamed to a [partial download](get_package_partial_download_path()) and only moved into the cache
+/// once its size and hash have been verified, otherwise it is removed. An interrupted download is resumed with an
+/// HTTP range request on the next attempt, if the server can't resume from where it stopped the download starts over.
+/// 
+/// The [download cache limit](crate::CkanRsConfig::download_cache_limit()) isn't applied, see [`evict_download_cache()`](super::cache::evict_download_cache()).
+/// 
 /// # Parameters
 /// - `config` - Required for getting download paths.
 /// - `packages` - List of packages to download.
-/// - `force` - Overwrite existing downloads.
+/// - `force` - Overwrite existing downloads, otherwise cached downloads are [verified](verify_cached_download()) and only replaced if invalid.
 /// 
 /// # Returns
-/// A vector of tuples containing a package to be downloaded and a result of the download.
+/// A vector of tuples containing a package to be downloaded and a result of the download, in the same order as `packages`.
 pub async fn download_packages_content<'info>(config: &crate::CkanRsConfig, packages: &[&'info Package], force: bool) 
 -> Vec<(&'info Package, Result<std::path::PathBuf, DownloadError>)> {
+	download_packages_content_inner(config, packages, force, None).await
+}
 
-	async fn download_package(config: &crate::CkanRsConfig, client: &reqwest::Client, package: &Package, force: bool)
+/// Same as [`download_packages_content()`] but sends [`Downloaded`](super::InstallProgress::Downloaded) progress to `progress` as content is received.
+pub async fn download_packages_content_with_progress<'info>(config: &crate::CkanRsConfig, packages: &[&'info Package], force: bool, progress: tokio::sync::mpsc::UnboundedSender<super::InstallProgress>) 
+-> Vec<(&'info Package, Result<std::path::PathBuf, DownloadError>)> {
+	download_packages_content_inner(config, packages, force, Some(progress)).await
+}
+
+async fn download_packages_content_inner<'info>(config: &crate::CkanRsConfig, packages: &[&'info Package], force: bool, progress: Option<super::ProgressSender>) 
+-> Vec<(&'info Package, Result<std::path::PathBuf, DownloadError>)> {
+
+	async fn download_package(config: &crate::CkanRsConfig, client: &reqwest::Client, package: &Package, force: bool, progress: Option<&super::ProgressSender>)
 	-> Result<std::path::PathBuf, DownloadError> {
+		use tokio::io::AsyncWriteExt;
+
 		let download_path = get_package_download_path(config, &package.identifier);
 		if download_path.exists() && !force {
-			log::info!("Package {} contents already downloaded, skipping.", &package.identifier);
-			return Ok(download_path);
+			match verify_cached_download(config, package).await {
+				Ok(()) => {
+					log::info!("Package {} contents already downloaded, skipping.", &package.identifier);
+					super::cache::mark_used(&download_path);
+					return Ok(download_path);
+				},
+				Err(e) => {
+					log::warn!("Cached download of package {} is invalid, downloading again: {}", &package.identifier, e);
+					tokio::fs::remove_file(&download_path).await?;
+				},
+			}
 		}
 		
 		let url = if let Some(url) = &package.download {
@@ -49,52 +145,323 @@ pub async fn download_packages_content<'info>(config: &crate::CkanRsConfig, pack
 		};
 		
 		tokio::fs::create_dir_all(download_path.with_file_name("")).await?;
-		let mut download_file = tokio::fs::File::create(&download_path).await?;
-	
-		log::info!("Downloading package {} from {}", package.identifier, url);
-		let content = client
-			.get(url)
-			.send()
-			.await?
-			.bytes()
-			.await?
-			.to_vec();
-	
-		log::info!("Writing package download to disk: {}", package.identifier);
-		tokio::io::copy(&mut content.as_slice(), &mut download_file).await?;
-		
-		if config.get_do_checksums() {
-			if let Some(package_hash) = &package.download_hash_sha256 {
-				let content_hash = sha256::digest(content.as_slice());
-				if String::from_utf8_lossy(package_hash) != content_hash {
-					return Err(DownloadError::DifferentHashes);
-				}
+		let partial_path = get_package_partial_download_path(config, &package.identifier);
+		if force {
+			match tokio::fs::remove_file(&partial_path).await {
+				Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+				_ => {},
+			}
+		}
+
+		let report = |bytes: u64, total: Option<u64>| {
+			if let Some(progress) = progress {
+				let _ = progress.send(super::InstallProgress::Downloaded { package: package.identifier.clone(), bytes, total });
+			}
+		};
+
+		let mut hasher = package.get_download_hash()
+			.filter(|_| config.get_do_checksums())
+			.map(|h| DownloadHasher::new(h.algorithm()));
+
+		/* Resume from whatever an earlier attempt managed to write */
+		let resume_from = match tokio::fs::metadata(&partial_path).await {
+			Ok(metadata) => metadata.len(),
+			Err(_) => 0,
+		};
+
+		let mut request = client.get(url);
+		if resume_from > 0 {
+			log::info!("Resuming download of package {} from {} at byte {}", package.identifier, url, resume_from);
+			request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_from));
+		} else {
+			log::info!("Downloading package {} from {}", package.identifier, url);
+		}
+		let mut response = request.send().await?;
+
+		let mut bytes = 0;
+		let resumed = resume_from > 0
+			&& response.status() == reqwest::StatusCode::PARTIAL_CONTENT
+			&& get_content_range_start(&response) == Some(resume_from);
+		let mut partial_file = if resumed {
+			if let Some(hasher) = &mut hasher {
+				hash_file(&partial_path, hasher).await?;
+			}
+			bytes = resume_from;
+			tokio::fs::OpenOptions::new().append(true).open(&partial_path).await?
+		} else if resume_from > 0 && matches!(response.status(), reqwest::StatusCode::PARTIAL_CONTENT | reqwest::StatusCode::RANGE_NOT_SATISFIABLE) {
+			/* Either the partial file is as long as or longer than the content or the server sent a different range,
+			the partial file can't be trusted so start over */
+			log::warn!("Server could not resume package {} from byte {}, restarting download.", package.identifier, resume_from);
+			tokio::fs::remove_file(&partial_path).await?;
+			response = client.get(url).send().await?.error_for_status()?;
+			tokio::fs::File::create(&partial_path).await?
+		} else {
+			/* Servers which ignore the range send the whole content */
+			response = response.error_for_status()?;
+			tokio::fs::File::create(&partial_path).await?
+		};
+
+		/* For a partial response the content length is only what remains */
+		let total = response.content_length().map(|len| len + bytes);
+		report(bytes, total);
+		while let Some(chunk) = response.chunk().await? {
+			if let Some(hasher) = &mut hasher {
+				hasher.update(&chunk);
 			}
-			else if let Some(_package_hash) = &package.download_hash_sha1 {
-				/* TODO: Sha1 hashing */
-				/* XXX: Sha1 crate doesn't work as the documentation for it says so we need to find a better crate for this */
-				unimplemented!("Sha1 hashing not yet implemented.")
+			partial_file.write_all(&chunk).await?;
+			bytes += chunk.len() as u64;
+			report(bytes, total);
+		}
+		partial_file.flush().await?;
+
+		if let Some(expected) = package.download_size {
+			if bytes != expected {
+				tokio::fs::remove_file(&partial_path).await?;
+				return Err(DownloadError::DifferentSize { expected, actual: bytes });
+			}
+		}
+
+		if let (Some(hasher), Some(expected)) = (hasher, package.get_download_hash()) {
+			let actual = hasher.finish();
+			if &actual != expected {
+				tokio::fs::remove_file(&partial_path).await?;
+				return Err(DownloadError::DifferentHashes { expected: expected.clone(), actual });
 			}
 		}
 
+		log::info!("Moving package download into cache: {}", package.identifier);
+		tokio::fs::rename(&partial_path, &download_path).await?;
+
 		Ok(download_path)
 	}
 
-	let mut results = Vec::<(&Package, Result<std::path::PathBuf, DownloadError>)>::new();
+	/// The host a package is downloaded from, packages without a valid download URL share an empty host.
+	fn get_download_host(package: &Package) -> String {
+		package.download.as_ref()
+			.and_then(|url| reqwest::Url::parse(url).ok())
+			.and_then(|url| url.host_str().map(|h| h.to_string()))
+			.unwrap_or_default()
+	}
 
 	let client = reqwest::Client::builder()
 		.https_only(config.https_only())
 		.build()
 		.expect("failed to create reqwest client.");
 
-	for package in packages {
-		results.push(
-			(
-				package, 
-				download_package(config, &client, package, force).await,
-			)
-		);
+	let global_limit = tokio::sync::Semaphore::new(config.download_limit());
+	let host_limits = packages.iter()
+		.map(|p| (get_download_host(p), tokio::sync::Semaphore::new(config.download_limit_per_host())))
+		.collect::<std::collections::HashMap<_, _>>();
+
+	let (client, global_limit, host_limits, progress) = (&client, &global_limit, &host_limits, progress.as_ref());
+	futures::future::join_all(packages.iter().map(|package| async move {
+		/* Always take the host permit first so a download never holds a global permit while waiting on its host */
+		let _host_permit = host_limits[&get_download_host(package)].acquire().await.expect("semaphore should not be closed.");
+		let _permit = global_limit.acquire().await.expect("semaphore should not be closed.");
+		(*package, download_package(config, client, package, force, progress).await)
+	})).await
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures;
+	use std::sync::{Arc, Mutex};
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use serde_json::json;
+
+	/// A package downloaded from `url` whose download is `content`.
+	fn package(identifier: &str, url: &str, content: &[u8]) -> Package {
+		test_fixtures::package(identifier, "1.0", json!({
+			"download": url,
+			"download_size": content.len(),
+			"download_hash": { "sha1": DownloadHash::compute(HashAlgorithm::Sha1, content).to_hex() },
+		}))
+	}
+
+	fn config(dir: &std::path::Path) -> crate::CkanRsConfig {
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_download_dir(dir.to_path_buf()));
+		config.set_https_only(false);
+		config
 	}
 
-	results
-}
\ No newline at end of file
+	/// How a test server answers range requests.
+	#[derive(Debug, Clone, Copy)]
+	enum RangeResponse {
+		Partial,
+		NotSatisfiable,
+		/// Sends the whole content as a partial response.
+		WrongStart,
+	}
+
+	/// Serves `content` at every path, returns the server and the `Range` header of each request.
+	async fn serve_content(content: Vec<u8>, range_response: RangeResponse) -> (std::net::SocketAddr, Arc<Mutex<Vec<Option<String>>>>) {
+		use tokio::io::AsyncWriteExt;
+
+		let ranges = Arc::new(Mutex::new(Vec::new()));
+		let (content, log) = (Arc::new(content), ranges.clone());
+		let server = test_fixtures::serve("127.0.0.1", move |request, mut stream| {
+			let (content, log) = (content.clone(), log.clone());
+			async move {
+				let range = request.headers.get("range").cloned();
+				log.lock().unwrap().push(range.clone());
+				let start = range.as_deref()
+					.and_then(|r| r.strip_prefix("bytes="))
+					.and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+				let len = content.len();
+				let data = match (start, range_response) {
+					(None, _) => test_fixtures::response("200 OK", &[("Content-Length", len.to_string())], &content),
+					(Some(start), RangeResponse::Partial) => test_fixtures::response("206 Partial Content", &[
+						("Content-Length", (len - start).to_string()),
+						("Content-Range", format!("bytes {start}-{}/{len}", len - 1)),
+					], &content[start..]),
+					(Some(_), RangeResponse::NotSatisfiable) => test_fixtures::response("416 Range Not Satisfiable", &[("Content-Length", "0".to_string())], b""),
+					(Some(_), RangeResponse::WrongStart) => test_fixtures::response("206 Partial Content", &[
+						("Content-Length", len.to_string()),
+						("Content-Range", format!("bytes 0-{}/{len}", len - 1)),
+					], &content),
+				};
+				let _ = stream.write_all(&data).await;
+			}
+		}).await;
+		(server, ranges)
+	}
+
+	#[tokio::test]
+	async fn downloads_are_streamed_and_resumed() {
+		let dir = tempfile::tempdir().unwrap();
+		let config = config(dir.path());
+		let content = (0..200_000).map(|i| i as u8).collect::<Vec<_>>();
+		let (server, ranges) = serve_content(content.clone(), RangeResponse::Partial).await;
+		let package = package("Mod", &format!("http://{server}/Mod.zip"), &content);
+		let (download_path, partial_path) = (get_package_download_path(&config, &package.identifier), get_package_partial_download_path(&config, &package.identifier));
+
+		let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+		let results = download_packages_content_with_progress(&config, &[&package], false, sender).await;
+		assert_eq!(results[0].1.as_ref().unwrap(), &download_path);
+		assert_eq!(std::fs::read(&download_path).unwrap(), content);
+		assert!(!partial_path.exists());
+		let mut reports = Vec::new();
+		while let Ok(crate::installation::InstallProgress::Downloaded { bytes, total, .. }) = receiver.try_recv() {
+			assert_eq!(total, Some(content.len() as u64));
+			reports.push(bytes);
+		}
+		assert_eq!(reports.first(), Some(&0));
+		assert_eq!(reports.last(), Some(&(content.len() as u64)));
+		assert!(reports.windows(2).all(|w| w[0] < w[1]));
+
+		/* Resume from the first half written by an interrupted attempt */
+		std::fs::remove_file(&download_path).unwrap();
+		std::fs::write(&partial_path, &content[..100_000]).unwrap();
+		let results = download_packages_content(&config, &[&package], false).await;
+		assert!(results[0].1.is_ok());
+		assert_eq!(std::fs::read(&download_path).unwrap(), content);
+		assert_eq!(*ranges.lock().unwrap(), vec![None, Some("bytes=100000-".to_string())]);
+	}
+
+	#[tokio::test]
+	async fn rejected_resumes_are_restarted() {
+		for range_response in [RangeResponse::NotSatisfiable, RangeResponse::WrongStart] {
+			let dir = tempfile::tempdir().unwrap();
+			let config = config(dir.path());
+			let content = b"the whole content".to_vec();
+			let (server, ranges) = serve_content(content.clone(), range_response).await;
+			let package = package("Mod", &format!("http://{server}/Mod.zip"), &content);
+			std::fs::write(get_package_partial_download_path(&config, &package.identifier), b"the ").unwrap();
+
+			let results = download_packages_content(&config, &[&package], false).await;
+			assert!(results[0].1.is_ok(), "{range_response:?}");
+			assert_eq!(std::fs::read(get_package_download_path(&config, &package.identifier)).unwrap(), content, "{range_response:?}");
+			assert_eq!(*ranges.lock().unwrap(), vec![Some("bytes=4-".to_string()), None], "{range_response:?}");
+		}
+	}
+
+	#[tokio::test]
+	async fn invalid_downloads_are_removed() {
+		let dir = tempfile::tempdir().unwrap();
+		let config = config(dir.path());
+
+		let (server, _) = serve_content(b"wrong content".to_vec(), RangeResponse::Partial).await;
+		let wrong_hash = package("Hash", &format!("http://{server}/Hash.zip"), b"right content");
+		let results = download_packages_content(&config, &[&wrong_hash], false).await;
+		assert!(matches!(results[0].1, Err(DownloadError::DifferentHashes { .. })));
+		assert!(!get_package_partial_download_path(&config, &wrong_hash.identifier).exists());
+		assert!(!get_package_download_path(&config, &wrong_hash.identifier).exists());
+
+		let (server, _) = serve_content(b"short".to_vec(), RangeResponse::Partial).await;
+		let wrong_size = package("Size", &format!("http://{server}/Size.zip"), b"right content");
+		let results = download_packages_content(&config, &[&wrong_size], false).await;
+		assert!(matches!(results[0].1, Err(DownloadError::DifferentSize { expected: 13, actual: 5 })));
+		assert!(!get_package_partial_download_path(&config, &wrong_size.identifier).exists());
+		assert!(!get_package_download_path(&config, &wrong_size.identifier).exists());
+	}
+
+	/// Connections open at once and the most seen.
+	#[derive(Default)]
+	struct Concurrency {
+		current: AtomicUsize,
+		max: AtomicUsize,
+	}
+
+	impl Concurrency {
+		fn enter(&self) {
+			let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+			self.max.fetch_max(current, Ordering::SeqCst);
+		}
+		fn exit(&self) {
+			self.current.fetch_sub(1, Ordering::SeqCst);
+		}
+	}
+
+	#[tokio::test]
+	async fn downloads_are_limited_per_host_and_globally() {
+		use tokio::io::AsyncWriteExt;
+
+		let dir = tempfile::tempdir().unwrap();
+		let mut config = config(dir.path());
+		assert!(config.set_download_limit(3));
+		assert!(config.set_download_limit_per_host(2));
+
+		let global = Arc::new(Concurrency::default());
+		let mut hosts = Vec::new();
+		for address in ["127.0.0.1", "127.0.0.2"] {
+			let host = Arc::new(Concurrency::default());
+			let (counter, global) = (host.clone(), global.clone());
+			let server = test_fixtures::serve(address, move |request, mut stream| {
+				let (host, global) = (counter.clone(), global.clone());
+				async move {
+					host.enter();
+					global.enter();
+					tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+					/* Leave before responding so the next download can't be counted early */
+					host.exit();
+					global.exit();
+					let body = request.path.into_bytes();
+					let _ = stream.write_all(&test_fixtures::response("200 OK", &[("Content-Length", body.len().to_string())], &body)).await;
+				}
+			}).await;
+			hosts.push((server, host));
+		}
+
+		/* Alternate between hosts so both are downloading at once */
+		let packages = (0..8)
+			.map(|i| {
+				let server = hosts[i % 2].0;
+				let path = format!("/Mod{i}.zip");
+				package(&format!("Mod{i}"), &format!("http://{server}{path}"), path.as_bytes())
+			})
+			.collect::<Vec<_>>();
+		let results = download_packages_content(&config, &packages.iter().collect::<Vec<_>>(), false).await;
+
+		assert_eq!(results.iter().map(|(p, _)| &p.identifier).collect::<Vec<_>>(), packages.iter().map(|p| &p.identifier).collect::<Vec<_>>());
+		for (i, (package, result)) in results.into_iter().enumerate() {
+			let path = result.unwrap();
+			assert_eq!(path, get_package_download_path(&config, &package.identifier));
+			assert_eq!(std::fs::read(path).unwrap(), format!("/Mod{i}.zip").into_bytes());
+		}
+		for (_, host) in &hosts {
+			assert_eq!(host.max.load(Ordering::SeqCst), 2);
+		}
+		assert_eq!(global.max.load(Ordering::SeqCst), 3);
+	}
+}
@@ -24,4 +24,7 @@ pub use config::CkanRsConfig;
 
 pub mod installation;
 pub mod relationship_resolver;
-pub mod game_instance;
\ No newline at end of file
+pub mod game_instance;
+
+#[cfg(test)]
+mod test_fixtures;
\ No newline at end of file
@@ -6,6 +6,40 @@ pub mod package;
 
 mod generation;
 pub use generation::generate_latest;
+pub use generation::GenerationProgress;
+
+mod repository;
+pub use repository::Repository;
+
+mod builds;
+pub use builds::BuildCatalog;
+pub use builds::OFFICIAL_BUILDS_URL;
+
+mod report;
+pub use report::GenerationReport;
+pub use report::RejectedEntry;
+pub use report::RejectionStage;
+
+mod storage;
+pub use storage::Compression;
+pub use storage::FormatStatus;
+pub use storage::MetaDBHeader;
+pub use storage::RepositorySource;
+pub use storage::FORMAT_VERSION;
+
+mod index;
+
+mod search;
+pub use search::SearchResult;
+
+mod query;
+pub use query::Filter;
+pub use query::PackageQuery;
+pub use query::SortBy;
+
+mod diff;
+pub use diff::MetaDBDiff;
+pub use diff::PackageChange;
 
 mod iterator;
 pub use iterator::KspVersionMatchesExt;
@@ -19,14 +53,36 @@ use serde::{Serialize, Deserialize};
 use package::{Package, PackageIdentifier, PackageVersion};
 
 /// A list of build numbers found in `buildID.txt` and their associated version strings.
+///
+/// This is the raw form found in `builds.json`, see [`BuildCatalog`] for the parsed versions.
 pub type BuildIDList = HashMap<i32, String>;
 
+/// Compiles the bundled `.ckan` JSON schema.
+pub(crate) fn compile_ckan_schema() -> jsonschema::JSONSchema {
+	jsonschema::JSONSchema::compile(
+		&serde_json::from_str(
+			include_str!("metadb/CKAN-json.schema")
+		).expect("schema should be valid json.")
+	).expect("schema should compile.")
+}
+
 /// A database of packages that can be installed to the game.
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct MetaDB {
 	packages: HashSet<Package>,
-	/* TODO: Store builds field seperately so we don't have to load the whole DB to get game versions. */
-	builds: BuildIDList,
+	/// Saved separately so game versions can be read without loading the whole DB.
+	#[serde(skip)]
+	builds: BuildCatalog,
+	/// Tracks the archive used for each repository so the DB can be updated incrementally.
+	repositories: HashMap<String, generation::ArchiveInfo>,
+	/// The repository each package in `packages` was taken from.
+	origins: HashMap<PackageIdentifier, String>,
+	/// Packages hidden by an identical package from a higher priority repository.
+	shadowed: HashMap<PackageIdentifier, Vec<(String, Package)>>,
+	/// When the packages were last generated or updated.
+	generated: Option<std::time::SystemTime>,
+	#[serde(skip)]
+	indexes: index::Indexes,
 }
 
 impl MetaDB {
@@ -36,7 +92,7 @@ impl MetaDB {
 	}
 
 	pub fn get_from_unique_id(&self, id: impl AsRef<PackageIdentifier>) -> Option<&Package> {
-		self.packages.iter().find(|package| package.identifier == *id.as_ref())
+		self.packages.get(id.as_ref())
 	}
 
 	pub fn get_from_identifier_and_version(&self, identifier: &str, version: &PackageVersion) -> Option<&Package> {
@@ -47,33 +103,12 @@ impl MetaDB {
 		self.get_from_unique_id(unique)
 	}
 
-	pub fn get_game_builds(&self) -> &BuildIDList {
+	pub fn get_game_builds(&self) -> &BuildCatalog {
 		&self.builds
 	}
 
-	/// Loads the MetaDB.
-	/// 
-	/// # Errors
-	/// - [`IO`](`crate::error::Error::IO`) when opening or reading from the file.
-	/// - [`Parse`](`crate::error::Error::Parse`) when deserializing the file, this is likely due to the DB format changing and so needs regenerating.
-	pub fn load_from_disk(config: &crate::CkanRsConfig) -> crate::Result<MetaDB> {
-		let path = config.data_dir().join("metadb.bin");
-		let mut f = std::fs::File::open(path)?;
-		let mut v = Vec::<u8>::new();
-		f.read_to_end(&mut v)?;
-		bincode::deserialize::<MetaDB>(&v).map_err(|_| crate::error::Error::Parse("Deserialize failed".to_string()))
-	}
-
-	/// Saves the MetaDB.
-	/// 
-	/// # Errors
-	/// - [`IO`](`crate::error::Error::IO`) when creating or writing to the file.
-	/// - [`Parse`](`crate::error::Error::Parse`) when serializing the file.
-	pub fn save_to_disk(&self, config: &crate::CkanRsConfig) -> crate::Result<()> {
-		let path = config.data_dir().join("metadb.bin");
-		let data = bincode::serialize(self).map_err(|_| crate::error::Error::Parse("Serialize failed".to_string()))?;
-		let mut f = std::fs::File::create(path)?;
-		f.write_all(&data)?;
-		Ok(())
+	/// When the packages were last generated or updated.
+	pub fn get_generation_time(&self) -> Option<std::time::SystemTime> {
+		self.generated
 	}
 }
\ No newline at end of file
@@ -0,0 +1,145 @@
+//! KSP build numbers and the game versions they belong to.
+//!
+//! The catalog is saved separately from the packages so it can be loaded without reading the whole [`MetaDB`].
+
+use super::*;
+use package::KspVersionReal;
+use std::collections::BTreeMap;
+
+/// URL of the `builds.json` maintained in the official CKAN-meta repository.
+pub const OFFICIAL_BUILDS_URL: &str = "https://raw.githubusercontent.com/KSP-CKAN/CKAN-meta/master/builds.json";
+
+/// A catalog of KSP build numbers found in `buildID.txt` and their associated versions.
+///
+/// Usually sourced from the `builds.json` of a repository while generating the [`MetaDB`]
+/// but can be loaded and refreshed on its own.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BuildCatalog {
+	builds: BTreeMap<i32, KspVersionReal>,
+}
+
+impl BuildCatalog {
+	/// Gets the version of a build.
+	pub fn get_version(&self, build_id: i32) -> Option<&KspVersionReal> {
+		self.builds.get(&build_id)
+	}
+
+	/// Gets every known version sorted from oldest to newest.
+	///
+	/// Builds of the same version are only listed once.
+	pub fn get_versions(&self) -> Vec<&KspVersionReal> {
+		let mut versions = self.builds.values().collect::<Vec<_>>();
+		versions.sort();
+		versions.dedup();
+		versions
+	}
+
+	/// Gets the version of the newest build.
+	pub fn get_latest(&self) -> Option<&KspVersionReal> {
+		self.builds.values().next_back()
+	}
+
+	/// Iterates over the builds sorted by build id.
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = (i32, &KspVersionReal)> {
+		self.builds.iter().map(|(id, version)| (*id, version))
+	}
+
+	pub fn len(&self) -> usize {
+		self.builds.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.builds.is_empty()
+	}
+
+	/// Adds or replaces a build.
+	///
+	/// Returns `true` if the catalog was changed.
+	pub fn insert(&mut self, build_id: i32, version: KspVersionReal) -> bool {
+		/* `KspVersionReal`'s Eq ignores the build number so compare it separately */
+		let changed = !self.builds.get(&build_id).is_some_and(|v| v == &version && v.build() == version.build());
+		self.builds.insert(build_id, version);
+		changed
+	}
+
+	/// Adds the builds from the contents of a `builds.json` file.
+	///
+	/// Entries with an invalid version string are skipped.
+	///
+	/// Returns `true` if the catalog was changed.
+	/// # Errors
+	/// - [`SerdeJSON`](crate::Error::SerdeJSON) when the file isn't valid JSON.
+	/// - [`Parse`](crate::Error::Parse) when the file doesn't contain a `builds` object.
+	pub fn update_from_json(&mut self, data: &[u8]) -> crate::Result<bool> {
+		let json: serde_json::Value = serde_json::from_slice(data)?;
+		let builds = json.as_object()
+			.and_then(|o| o.get("builds"))
+			.ok_or_else(|| crate::Error::Parse("builds.json root object should contain key \"builds\"".to_string()))?;
+		let builds: BuildIDList = serde_json::from_value(builds.clone())?;
+
+		let mut changed = false;
+		for (id, version) in builds {
+			match KspVersionReal::new(&version) {
+				Ok(v) => changed |= self.insert(id, v),
+				Err(e) => log::warn!("Skipping build {} with invalid version \"{}\": {}", id, version, e),
+			}
+		}
+		Ok(changed)
+	}
+
+	/// Downloads the latest `builds.json` from `url` and adds its builds.
+	///
+	/// Use [`OFFICIAL_BUILDS_URL`] to refresh from the official repository.
+	///
+	/// Returns `true` if the catalog was changed.
+	pub async fn update_from_url(&mut self, url: &str) -> crate::Result<bool> {
+		log::trace!("Downloading builds from {}", url);
+		let data = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+		self.update_from_json(&data)
+	}
+
+	/// Loads the catalog.
+	///
+	/// # Errors
+	/// - [`IO`](`crate::error::Error::IO`) when opening or reading from the file.
+	/// - [`Parse`](`crate::error::Error::Parse`) when deserializing the file.
+	pub fn load_from_disk(config: &crate::CkanRsConfig) -> crate::Result<BuildCatalog> {
+		let path = config.data_dir().join("builds.bin");
+		let v = std::fs::read(path)?;
+		bincode::deserialize::<BuildCatalog>(&v).map_err(|_| crate::error::Error::Parse("Deserialize failed".to_string()))
+	}
+
+	/// Saves the catalog.
+	///
+	/// # Errors
+	/// - [`IO`](`crate::error::Error::IO`) when creating or writing to the file.
+	/// - [`Parse`](`crate::error::Error::Parse`) when serializing the file.
+	pub fn save_to_disk(&self, config: &crate::CkanRsConfig) -> crate::Result<()> {
+		let path = config.data_dir().join("builds.bin");
+		let data = bincode::serialize(self).map_err(|_| crate::error::Error::Parse("Serialize failed".to_string()))?;
+		let mut f = std::fs::File::create(path)?;
+		f.write_all(&data)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn catalog_lookups() {
+		let mut catalog = BuildCatalog::default();
+		let json = r#"{"builds": {"3190": "1.12.5.3190", "3173": "1.12.3.3173", "3174": "1.12.3.3174", "9999": "not a version"}}"#;
+		assert!(catalog.update_from_json(json.as_bytes()).unwrap());
+		assert!(!catalog.update_from_json(json.as_bytes()).unwrap());
+
+		assert_eq!(catalog.len(), 3);
+		assert_eq!(catalog.get_version(3173), Some(&KspVersionReal::new("1.12.3").unwrap()));
+		assert!(catalog.get_version(9999).is_none());
+		assert_eq!(catalog.get_latest(), Some(&KspVersionReal::new("1.12.5").unwrap()));
+		assert_eq!(catalog.get_versions(), vec![&KspVersionReal::new("1.12.3").unwrap(), &KspVersionReal::new("1.12.5").unwrap()]);
+
+		assert!(catalog.update_from_json(br#"{"not_builds": {}}"#).is_err());
+	}
+}
@@ -0,0 +1,139 @@
+//! Comparing two snapshots of the MetaDB.
+
+use super::*;
+use package::KspVersionBounds;
+use std::collections::BTreeMap;
+
+/// How a single package differs between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackageChange {
+	/// A version of an identifier which wasn't in the old DB at all.
+	NewIdentifier,
+	/// A new version of an identifier which was already in the old DB.
+	NewVersion,
+	/// A version which was removed while other versions of the identifier remain.
+	RemovedVersion,
+	/// A version of an identifier which is no longer in the DB at all.
+	RemovedIdentifier,
+	/// The game versions the package is compatible with changed.
+	KspCompatibilityChanged {
+		old: KspVersionBounds,
+		old_strict: bool,
+		new: KspVersionBounds,
+		new_strict: bool,
+	},
+}
+
+/// The changes between two snapshots of the MetaDB, created by [`MetaDB::diff()`].
+#[derive(Debug, Default, Clone)]
+pub struct MetaDBDiff {
+	/// Every changed package, sorted by identifier and version.
+	pub changes: BTreeMap<PackageIdentifier, PackageChange>,
+}
+
+impl MetaDBDiff {
+	/// Returns `true` if the snapshots contain the same packages.
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+
+	/// Iterates over the packages with a change matching `filter`.
+	pub fn filter<'a>(&'a self, filter: impl Fn(&PackageChange) -> bool + 'a) -> impl Iterator<Item = (&'a PackageIdentifier, &'a PackageChange)> + 'a {
+		self.changes.iter().filter(move |(_, c)| filter(c))
+	}
+
+	/// Gets the versions of `identifier` which were added, newest first.
+	///
+	/// Useful for notifying about updates to installed packages.
+	pub fn get_new_versions(&self, identifier: &str) -> Vec<&PackageIdentifier> {
+		self.changes.iter()
+			.filter(|(id, c)| id.identifier == identifier && matches!(c, PackageChange::NewIdentifier | PackageChange::NewVersion))
+			.map(|(id, _)| id)
+			.rev()
+			.collect()
+	}
+}
+
+impl MetaDB {
+	/// Gets the changes needed to turn `old` into this DB.
+	pub fn diff(&self, old: &MetaDB) -> MetaDBDiff {
+		let mut changes = BTreeMap::new();
+
+		for package in &self.packages {
+			let id = &package.identifier;
+			let change = match old.packages.get(id) {
+				Some(old_package) => {
+					if old_package.ksp_version == package.ksp_version && old_package.ksp_version_strict == package.ksp_version_strict {
+						continue;
+					}
+					PackageChange::KspCompatibilityChanged {
+						old: old_package.ksp_version.clone(),
+						old_strict: old_package.ksp_version_strict,
+						new: package.ksp_version.clone(),
+						new_strict: package.ksp_version_strict,
+					}
+				},
+				None if old.get_versions(&id.identifier).is_empty() => PackageChange::NewIdentifier,
+				None => PackageChange::NewVersion,
+			};
+			changes.insert(id.clone(), change);
+		}
+
+		for package in &old.packages {
+			let id = &package.identifier;
+			if self.packages.contains(id) {
+				continue;
+			}
+			let change = if self.get_versions(&id.identifier).is_empty() {
+				PackageChange::RemovedIdentifier
+			} else {
+				PackageChange::RemovedVersion
+			};
+			changes.insert(id.clone(), change);
+		}
+
+		MetaDBDiff { changes }
+	}
+
+	/// Gets the changes since the MetaDB saved to disk.
+	///
+	/// # Errors
+	/// Same as [`load_from_disk()`](MetaDB::load_from_disk()).
+	pub fn diff_with_saved(&self, config: &crate::CkanRsConfig) -> crate::Result<MetaDBDiff> {
+		Ok(self.diff(&MetaDB::load_from_disk(config)?))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::{package, id};
+	use serde_json::json;
+
+	#[test]
+	fn diff_reports_changes() {
+		let mut old = MetaDB::default();
+		old.put_package(package("A", "1.0", json!({ "ksp_version": "1.12" })));
+		old.put_package(package("B", "1.0", json!({ "ksp_version": "1.11" })));
+		old.put_package(package("C", "1.0", json!({ "ksp_version": "1.12" })));
+		old.put_package(package("C", "1.1", json!({ "ksp_version": "1.12" })));
+		old.put_package(package("D", "1.0", json!({ "ksp_version": "1.12" })));
+
+		let mut new = MetaDB::default();
+		new.put_package(package("A", "1.0", json!({ "ksp_version": "1.12" })));
+		new.put_package(package("A", "1.1", json!({ "ksp_version": "1.12" })));
+		new.put_package(package("B", "1.0", json!({ "ksp_version": "1.12" })));
+		new.put_package(package("C", "1.1", json!({ "ksp_version": "1.12" })));
+		new.put_package(package("E", "1.0", json!({ "ksp_version": "1.12" })));
+
+		let diff = new.diff(&old);
+		assert_eq!(diff.changes.len(), 5);
+		assert_eq!(diff.changes[&id("A", "1.1")], PackageChange::NewVersion);
+		assert!(matches!(diff.changes[&id("B", "1.0")], PackageChange::KspCompatibilityChanged { .. }));
+		assert_eq!(diff.changes[&id("C", "1.0")], PackageChange::RemovedVersion);
+		assert_eq!(diff.changes[&id("D", "1.0")], PackageChange::RemovedIdentifier);
+		assert_eq!(diff.changes[&id("E", "1.0")], PackageChange::NewIdentifier);
+		assert_eq!(diff.get_new_versions("A"), vec![&id("A", "1.1")]);
+		assert!(new.diff(&new).is_empty());
+	}
+}
@@ -2,110 +2,657 @@ use super::*;
 use package::*;
 use crate::Error::Parse;
 
-/// Gets the lastest MetaDB .tar.gz archive as bytes
-async fn get_latest_archive() -> crate::Result<Vec<u8>> {
-	/* TODO: Latest archive URL not hardcoded instead in CkanRsConfig */
-	log::trace!("Downloading latest MetaDB.");
-	Ok(reqwest::get("https://github.com/KSP-CKAN/CKAN-meta/archive/master.tar.gz").await?.bytes().await.map(|v| v.to_vec())?)
+/// Information about the archive a repository was last generated or updated from.
+///
+/// Used to skip downloading and parsing work when nothing has changed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(super) struct ArchiveInfo {
+	/// Priority of the repository when it was processed.
+	priority: i32,
+	/// `ETag` header returned with the archive.
+	etag: Option<String>,
+	/// `Last-Modified` header returned with the archive.
+	last_modified: Option<String>,
+	/// SHA-256 digest of the compressed archive.
+	hash: Option<String>,
+	/// Entries read from the archive keyed by their path.
+	entries: HashMap<String, EntryInfo>,
 }
 
-/// Download and generate the latest MetaDB.
-pub async fn generate_latest() -> crate::Result<MetaDB> {
+/// A single file read from the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EntryInfo {
+	/// SHA-256 digest of the entry's contents.
+	hash: String,
+	/// The package created from the entry, `None` if the entry was rejected.
+	identifier: Option<PackageIdentifier>,
+	/// Why the entry was rejected.
+	rejection: Option<RejectedEntry>,
+}
+
+impl ArchiveInfo {
+	pub(super) fn priority(&self) -> i32 {
+		self.priority
+	}
+
+	pub(super) fn hash(&self) -> Option<&String> {
+		self.hash.as_ref()
+	}
+
+	pub(super) fn rejected_entries(&self) -> impl Iterator<Item = &RejectedEntry> {
+		self.entries.values().filter_map(|e| e.rejection.as_ref())
+	}
+}
+
+/// Progress of [`update_latest_with_progress()`](MetaDB::update_latest_with_progress()).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationProgress {
+	/// Bytes of a repository's archive received so far, `total` is `None` when the server doesn't report the size.
+	Downloaded { repository: String, bytes: u64, total: Option<u64> },
+	/// Entries of a repository read so far, including unchanged entries and files which aren't packages.
+	Processed { repository: String, entries: usize },
+}
+
+type ProgressSender = tokio::sync::mpsc::UnboundedSender<GenerationProgress>;
+
+/// Number of downloaded chunks buffered between the download and the archive reader.
+const ARCHIVE_CHUNK_BUFFER: usize = 16;
+
+/// Requests the latest .tar.gz archive of a repository.
+///
+/// Returns `None` when the server reports the archive hasn't changed since `info` was recorded.
+async fn request_archive(repository: &Repository, info: &ArchiveInfo) -> crate::Result<Option<reqwest::Response>> {
+	use reqwest::header::*;
+	log::trace!("Downloading repository {} from {}", repository.name, repository.url);
+
+	let mut request = reqwest::Client::new().get(&repository.url);
+	if let Some(etag) = &info.etag {
+		request = request.header(IF_NONE_MATCH, etag);
+	}
+	if let Some(last_modified) = &info.last_modified {
+		request = request.header(IF_MODIFIED_SINCE, last_modified);
+	}
+
+	let response = request.send().await?;
+	if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+		return Ok(None)
+	}
+	Ok(Some(response.error_for_status()?))
+}
+
+/// Sends the body of `response` to `sender` as it arrives.
+///
+/// Returns the SHA-256 digest of the body.
+async fn stream_archive(mut response: reqwest::Response, sender: tokio::sync::mpsc::Sender<std::io::Result<Vec<u8>>>, repository: &str, progress: Option<&ProgressSender>) -> crate::Result<String> {
+	use sha2::Digest;
+	let total = response.content_length();
+	let mut hasher = sha2::Sha256::new();
+	let mut bytes = 0u64;
+
+	loop {
+		let chunk = match response.chunk().await {
+			Ok(Some(chunk)) => chunk,
+			Ok(None) => break,
+			Err(e) => {
+				let _ = sender.send(Err(std::io::Error::other(e.to_string()))).await;
+				return Err(e.into())
+			},
+		};
+
+		hasher.update(&chunk);
+		bytes += chunk.len() as u64;
+		if let Some(progress) = progress {
+			let _ = progress.send(GenerationProgress::Downloaded { repository: repository.to_string(), bytes, total });
+		}
+
+		if sender.send(Ok(chunk.to_vec())).await.is_err() {
+			/* The reader stopped early, it will report why. */
+			break;
+		}
+	}
+
+	Ok(hex::encode(hasher.finalize()))
+}
+
+/// Reads the chunks sent by [`stream_archive()`] from a blocking thread.
+struct ChannelReader {
+	receiver: tokio::sync::mpsc::Receiver<std::io::Result<Vec<u8>>>,
+	chunk: Vec<u8>,
+	position: usize,
+}
+
+impl std::io::Read for ChannelReader {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		while self.position == self.chunk.len() {
+			match self.receiver.blocking_recv() {
+				Some(chunk) => {
+					self.chunk = chunk?;
+					self.position = 0;
+				},
+				None => return Ok(0),
+			}
+		}
+
+		let n = buf.len().min(self.chunk.len() - self.position);
+		buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+		self.position += n;
+		Ok(n)
+	}
+}
+
+/// Download and generate the latest MetaDB from the repositories in `config`.
+///
+/// Returns the DB along with a report of the entries which were rejected.
+pub async fn generate_latest(config: &crate::CkanRsConfig) -> crate::Result<(MetaDB, GenerationReport)> {
 	log::trace!("Generating latest MetaDB.");
-	let archive_data = get_latest_archive().await?;
-	let mut gz = flate2::bufread::GzDecoder::new(archive_data.as_slice());
-	let mut v = Vec::<u8>::new();
-	gz.read_to_end(&mut v)?;
-	MetaDB::generate_from_archive(
-		&mut tar::Archive::new(v.as_slice()), 
-		true
-	)
+	let mut db = MetaDB::default();
+	db.update_latest(config).await?;
+	let report = db.get_generation_report();
+	Ok((db, report))
+}
+
+/// State kept while updating the packages of a single repository.
+///
+/// Reading a repository only changes this, the DB is patched by [`finish_repository_update()`](MetaDB::finish_repository_update())
+/// so a repository can be read on another thread without moving the DB there.
+struct RepositoryUpdate {
+	repository: Repository,
+	schema: Option<jsonschema::JSONSchema>,
+	/// Entries from the previous update, entries are moved out of here as they are processed.
+	previous_entries: HashMap<String, EntryInfo>,
+	entries: HashMap<String, EntryInfo>,
+	/// Changed entries waiting to be parsed, see [`PARSE_BATCH_SIZE`].
+	pending: Vec<PendingEntry>,
+	/// Changes to the repository's packages in the order they were read.
+	package_changes: Vec<PackageChange>,
+	/// Builds read from the repository's `builds.json`.
+	builds: BuildCatalog,
+	changed: bool,
+	progress: Option<ProgressSender>,
+	entries_read: usize,
+}
+
+/// A change to the packages of a repository waiting to be applied to the DB.
+enum PackageChange {
+	Removed(PackageIdentifier),
+	Added(Box<Package>),
 }
 
+/// An entry which has been read from a repository but not parsed yet.
+struct PendingEntry {
+	path: String,
+	hash: String,
+	buffer: Vec<u8>,
+}
+
+/// Number of changed entries parsed together, this bounds the memory held by unparsed entries.
+const PARSE_BATCH_SIZE: usize = 512;
+
 impl MetaDB {
 	/// Creates a new MetaDB using a tar archive.
 	/// # Parameters
 	/// - `archive` - A tarball containing the metadb json files, should *not* be compressed.
-	/// - `do_validation` - Usually enabled when the repo can't be trusted to validate their ckans. should be `false` for most cases as it is slow.
-	pub fn generate_from_archive<R>(archive: &mut tar::Archive<R>, do_validation: bool) -> crate::Result<Self>
+	/// - `do_validation` - Should be enabled when the repo can't be trusted to validate their ckans. validation is spread across all cores along with parsing.
+	///
+	/// Returns the DB along with a report of the entries which were rejected.
+	pub fn generate_from_archive<R>(archive: &mut tar::Archive<R>, do_validation: bool) -> crate::Result<(Self, GenerationReport)>
 	where R: std::io::Read
 	{
 		log::trace!("Generating MetaDB from given archive.");
-		/* TODO: Determine if this is IO or CPU bound causing it to take 15 sec to generate. */
-		/* TODO: Some entries fail when validated against the schema, should this happen? surely the remote repo
-		doesn't have incorrect entries? */
+		let mut db = Self::default();
+		db.update_from_archive(&Repository::default(), archive, do_validation)?;
+		let report = db.get_generation_report();
+		Ok((db, report))
+	}
+
+	/// Creates a new MetaDB without any network access.
+	///
+	/// See [`update_from_path()`](MetaDB::update_from_path()) for the supported sources.
+	/// # Parameters
+	/// - `path` - The local source of the metadb json files.
+	/// - `do_validation` - Should be enabled when the repo can't be trusted to validate their ckans. validation is spread across all cores along with parsing.
+	///
+	/// Returns the DB along with a report of the entries which were rejected.
+	pub fn generate_from_path(path: impl AsRef<std::path::Path>, do_validation: bool) -> crate::Result<(Self, GenerationReport)> {
+		let path = path.as_ref();
+		log::trace!("Generating MetaDB from {}", path.display());
+		let repository = Repository {
+			local_path: Some(path.to_path_buf()),
+			..Default::default()
+		};
+		let mut db = Self::default();
+		db.update_from_path(&repository, path, do_validation)?;
+		let report = db.get_generation_report();
+		Ok((db, report))
+	}
+
+	/// Updates the MetaDB to the latest archives of the repositories in `config`.
+	///
+	/// Archives are only downloaded if they have changed since the last update,
+	/// and then only the entries which were added, changed or removed are processed.
+	/// Packages from repositories no longer present in `config` are removed.
+	///
+	/// Archives are processed as they are downloaded so they are never held in memory as a whole.
+	///
+	/// Repositories with a [`local_path`](Repository::local_path) are read using [`update_from_path()`](MetaDB::update_from_path()).
+	///
+	/// Each repository is applied once it has been read,
+	/// if the returned future is dropped the repository being read at the time is left as it was.
+	///
+	/// Returns `true` if the MetaDB was changed.
+	pub async fn update_latest(&mut self, config: &crate::CkanRsConfig) -> crate::Result<bool> {
+		self.update_latest_inner(config, None).await
+	}
+
+	/// Same as [`update_latest()`](MetaDB::update_latest()) but sends progress to `progress` as repositories are downloaded and read.
+	pub async fn update_latest_with_progress(&mut self, config: &crate::CkanRsConfig, progress: tokio::sync::mpsc::UnboundedSender<GenerationProgress>) -> crate::Result<bool> {
+		self.update_latest_inner(config, Some(progress)).await
+	}
+
+	async fn update_latest_inner(&mut self, config: &crate::CkanRsConfig, progress: Option<ProgressSender>) -> crate::Result<bool> {
+		log::trace!("Updating MetaDB.");
+		let mut changed = false;
+
+		let removed_repositories = self.repositories.keys()
+			.filter(|name| !config.repositories().iter().any(|r| &&r.name == name))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		for name in removed_repositories {
+			log::info!("Removing packages from repository {}", name);
+			changed |= self.remove_repository(&name);
+		}
+
+		for repository in config.repositories() {
+			if self.repositories.get(&repository.name).is_some_and(|info| info.priority != repository.priority) {
+				log::info!("Priority of repository {} changed, regenerating its packages.", repository.name);
+				changed |= self.remove_repository(&repository.name);
+			}
+
+			if let Some(path) = &repository.local_path {
+				changed |= self.update_repository(repository, true, progress.clone(), |update| update.read_path(path))?;
+				continue;
+			}
+
+			let info = self.repositories.get(&repository.name).cloned().unwrap_or_default();
+			let response = match request_archive(repository, &info).await? {
+				Some(r) => r,
+				None => {
+					log::info!("Repository {} has not been modified since the last update.", repository.name);
+					continue;
+				},
+			};
+
+			let header = |name: reqwest::header::HeaderName| response.headers().get(name).and_then(|v| v.to_str().ok()).map(|s| s.to_string());
+			let etag = header(reqwest::header::ETAG);
+			let last_modified = header(reqwest::header::LAST_MODIFIED);
+
+			let (sender, receiver) = tokio::sync::mpsc::channel(ARCHIVE_CHUNK_BUFFER);
+			let download = stream_archive(response, sender, &repository.name, progress.as_ref());
+
+			let mut update = self.begin_repository_update(repository, true, progress.clone());
+			let processing = tokio::task::spawn_blocking(move || {
+				let read = |update: &mut RepositoryUpdate| -> crate::Result<()> {
+					let reader = ChannelReader { receiver, chunk: Vec::new(), position: 0 };
+					let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+					update.read_tar(&mut archive)?;
+					/* Read anything after the end of the tarball so the whole download is hashed and checked. */
+					std::io::copy(&mut archive.into_inner(), &mut std::io::sink())?;
+					Ok(())
+				};
+				let result = read(&mut update);
+				(update, result)
+			});
+
+			let (hash, processed) = tokio::join!(download, processing);
+			let (update, result) = processed.expect("archive processing should not panic.");
+			changed |= self.finish_repository_update(update, result)?;
+			let hash = hash?;
+
+			let info = self.repositories.entry(repository.name.clone()).or_default();
+			info.etag = etag;
+			info.last_modified = last_modified;
+			info.hash = Some(hash);
+		}
+
+		if self.builds.is_empty() {
+			log::warn!("No repository contained builds.json, game versions will be unavailable.");
+		}
+
+		Ok(changed)
+	}
+
+	/// Patches the packages from `repository` in place using a local source.
+	///
+	/// `path` can be any of the following.
+	/// - A directory such as a checkout of CKAN-meta or a folder of loose `.ckan` files.
+	/// - A `.tar.gz`, `.tgz` or `.tar` archive.
+	/// - A `.zip` archive.
+	///
+	/// See [`update_from_archive()`](MetaDB::update_from_archive()) for details on how the packages are patched.
+	/// # Errors
+	/// - [`Parse`](crate::Error::Parse) when `path` is a file with an unsupported extension.
+	/// - [`IO`](crate::Error::IO) and [`Zip`](crate::Error::Zip) when reading the source.
+	pub fn update_from_path(&mut self, repository: &Repository, path: impl AsRef<std::path::Path>, do_validation: bool) -> crate::Result<bool> {
+		self.update_repository(repository, do_validation, None, |update| update.read_path(path.as_ref()))
+	}
+
+	/// Patches the packages from `repository` in place using a tar archive.
+	///
+	/// Entries which are unchanged since the last archive was processed are skipped,
+	/// packages from entries no longer present in the archive are removed.
+	///
+	/// Returns `true` if any packages or builds were changed.
+	/// # Parameters
+	/// - `repository` - The repository the archive belongs to.
+	/// - `archive` - A tarball containing the metadb json files, should *not* be compressed.
+	/// - `do_validation` - Should be enabled when the repo can't be trusted to validate their ckans. validation is spread across all cores along with parsing.
+	pub fn update_from_archive<R>(&mut self, repository: &Repository, archive: &mut tar::Archive<R>, do_validation: bool) -> crate::Result<bool>
+	where R: std::io::Read
+	{
+		log::trace!("Updating repository {} from given archive.", repository.name);
+		self.update_repository(repository, do_validation, None, |update| update.read_tar(archive))
+	}
+
+	/// Patches the packages from `repository` in place using a zip archive.
+	///
+	/// See [`update_from_archive()`](MetaDB::update_from_archive()) for details on how the packages are patched.
+	pub fn update_from_zip<R>(&mut self, repository: &Repository, archive: &mut zip::ZipArchive<R>, do_validation: bool) -> crate::Result<bool>
+	where R: std::io::Read + std::io::Seek
+	{
+		log::trace!("Updating repository {} from given zip archive.", repository.name);
+		self.update_repository(repository, do_validation, None, |update| update.read_zip(archive))
+	}
+
+	/// Patches the packages from `repository` in place using a directory.
+	///
+	/// The directory is searched recursively so both a checkout of a repository and a folder of loose `.ckan` files can be used.
+	///
+	/// See [`update_from_archive()`](MetaDB::update_from_archive()) for details on how the packages are patched.
+	pub fn update_from_directory(&mut self, repository: &Repository, directory: impl AsRef<std::path::Path>, do_validation: bool) -> crate::Result<bool> {
+		self.update_repository(repository, do_validation, None, |update| update.read_directory(directory.as_ref()))
+	}
+
+	/// Runs `read` between starting and finishing an update of `repository`.
+	///
+	/// If `read` fails the entries processed so far and those not reached are kept, their packages are still in the DB.
+	///
+	/// Returns `true` if any packages or builds were changed.
+	fn update_repository(&mut self, repository: &Repository, do_validation: bool, progress: Option<ProgressSender>, read: impl FnOnce(&mut RepositoryUpdate) -> crate::Result<()>) -> crate::Result<bool> {
+		let mut update = self.begin_repository_update(repository, do_validation, progress);
+		let result = read(&mut update);
+		self.finish_repository_update(update, result)
+	}
+
+	fn begin_repository_update(&self, repository: &Repository, do_validation: bool, progress: Option<ProgressSender>) -> RepositoryUpdate {
+		let previous_entries = self.repositories.get(&repository.name).map(|info| info.entries.clone()).unwrap_or_default();
+		let schema = do_validation.then(compile_ckan_schema);
+
+		RepositoryUpdate {
+			repository: repository.clone(),
+			schema,
+			previous_entries,
+			entries: Default::default(),
+			pending: Default::default(),
+			package_changes: Default::default(),
+			builds: Default::default(),
+			changed: false,
+			progress,
+			entries_read: 0,
+		}
+	}
+
+	/// Applies the changes read by `update` to the DB.
+	///
+	/// Packages from entries which were not seen are removed, unless `result` is an error
+	/// in which case the entries which weren't reached are kept.
+	///
+	/// Returns `true` if any packages or builds were changed.
+	fn finish_repository_update(&mut self, mut update: RepositoryUpdate, result: crate::Result<()>) -> crate::Result<bool> {
+		update.parse_pending_entries();
+
+		let name = update.repository.name.clone();
+		self.repositories.insert(name.clone(), ArchiveInfo { priority: update.repository.priority, ..Default::default() });
+		for change in std::mem::take(&mut update.package_changes) {
+			match change {
+				PackageChange::Removed(identifier) => self.remove_package(&name, &identifier),
+				PackageChange::Added(package) => self.insert_package(&update.repository, *package),
+			}
+		}
+		for (build_id, version) in update.builds.iter() {
+			update.changed |= self.builds.insert(build_id, version.clone());
+		}
+
+		if let Err(e) = result {
+			update.entries.extend(update.previous_entries);
+			self.repositories.get_mut(&name).expect("repository info should be inserted before applying changes.").entries = update.entries;
+			return Err(e)
+		}
+
+		for (path, entry) in update.previous_entries.drain() {
+			log::trace!("Entry {} was removed from repository {}", path, name);
+			update.changed = true;
+			if let Some(identifier) = &entry.identifier {
+				self.remove_package(&name, identifier);
+			}
+		}
+
+		self.repositories.get_mut(&name).expect("repository info should be inserted before applying changes.").entries = update.entries;
+		self.generated = Some(std::time::SystemTime::now());
+		Ok(update.changed)
+	}
 
-		let mut packages = HashSet::<Package>::new();
-		let mut builds = HashMap::<i32, String>::new();
+	/// Removes a repository and all of its packages.
+	///
+	/// Returns `true` if the repository was present.
+	fn remove_repository(&mut self, name: &str) -> bool {
+		let info = match self.repositories.get(name) {
+			Some(info) => info.clone(),
+			None => return false,
+		};
+		for identifier in info.entries.values().filter_map(|e| e.identifier.as_ref()) {
+			self.remove_package(name, identifier);
+		}
+		self.repositories.remove(name);
+		true
+	}
+
+	/// Creates a package from a single archive entry.
+	fn read_entry(repository: &Repository, path: &str, buffer: &[u8], schema: Option<&jsonschema::JSONSchema>) -> Result<Package, RejectedEntry> {
+		let reject = |identifier: Option<String>, stage: RejectionStage, error: String| RejectedEntry {
+			repository: repository.name.clone(),
+			path: path.to_string(),
+			identifier,
+			stage,
+			error,
+		};
+
+		let json = serde_json::from_slice::<serde_json::Value>(buffer)
+			.map_err(|e| reject(None, RejectionStage::Json, e.to_string()))?;
+		let identifier = json.get("identifier").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+		if let Some(schema) = schema {
+			if let Err(errors) = schema.validate(&json) {
+				let error = errors.map(|e| e.to_string()).collect::<Vec<_>>().join("; ");
+				return Err(reject(identifier, RejectionStage::Schema, error))
+			}
+		}
+
+		Package::read_from_json(json).map_err(|e| reject(identifier, RejectionStage::Package, e.to_string()))
+	}
+}
+
+impl RepositoryUpdate {
+	/// Reads a local source, see [`update_from_path()`](MetaDB::update_from_path()).
+	fn read_path(&mut self, path: &std::path::Path) -> crate::Result<()> {
+		if path.is_dir() {
+			return self.read_directory(path)
+		}
+
+		let file_name = path.file_name().map(|f| f.to_string_lossy().to_lowercase()).unwrap_or_default();
+		if !(file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") || file_name.ends_with(".tar") || file_name.ends_with(".zip")) {
+			return Err(Parse(format!("unsupported metadb source {}", path.display())))
+		}
 
-		let compiled_schema = do_validation.then(||
-			jsonschema::JSONSchema::compile(
-				&serde_json::from_str(
-					include_str!("CKAN-json.schema")
-				).expect("schema should be valid json.")
-			).expect("schema should compile.")
-		);
+		let file = std::io::BufReader::new(std::fs::File::open(path)?);
+		if file_name.ends_with(".zip") {
+			self.read_zip(&mut zip::ZipArchive::new(file)?)
+		} else if file_name.ends_with(".tar") {
+			self.read_tar(&mut tar::Archive::new(file))
+		} else {
+			self.read_tar(&mut tar::Archive::new(flate2::bufread::GzDecoder::new(file)))
+		}
+	}
 
-		for (i, entry) in archive.entries()?.enumerate() {
+	fn read_tar<R>(&mut self, archive: &mut tar::Archive<R>) -> crate::Result<()>
+	where R: std::io::Read
+	{
+		/* TODO: Some entries fail when validated against the schema, should this happen? surely the remote repo
+		doesn't have incorrect entries? */
+		for entry in archive.entries()? {
 			let mut entry = entry.map_err(|_| Parse("tar archive entries unreadable".to_string()))?;
 
 			if entry.header().entry_type() == tar::EntryType::Directory {
 				continue;
 			}
 
-			if entry.path()?.to_string_lossy() == "CKAN-meta-master/builds.json" {
-				log::trace!("Processing builds.json");
-				let mut buffer = Vec::<u8>::new();
-				entry.read_to_end(&mut buffer)?;
-				let json: serde_json::Value = serde_json::from_str(&String::from_utf8(buffer).unwrap())?;
-				builds = serde_json::from_value(
-					json.as_object().expect("builds.json root should be an object.")
-					.get("builds").expect("builds.json root object should contain key \"builds\".")
-					.clone()
-				)?;
+			let path = entry.path()?.to_string_lossy().to_string();
+			let mut buffer = Vec::<u8>::new();
+			entry.read_to_end(&mut buffer)?;
+			self.process_entry(path, buffer)?;
+		}
+		Ok(())
+	}
+
+	fn read_zip<R>(&mut self, archive: &mut zip::ZipArchive<R>) -> crate::Result<()>
+	where R: std::io::Read + std::io::Seek
+	{
+		for i in 0..archive.len() {
+			let mut entry = archive.by_index(i)?;
+
+			if entry.is_dir() {
 				continue;
 			}
 
-			let json = {
-				let mut buffer = Vec::<u8>::new();
-				entry.read_to_end(&mut buffer)?;
-				match serde_json::from_slice::<serde_json::Value>(&buffer) {
-					Ok(v) => v,
-					Err(e) => {
-						log::warn!("Couldn't process entry {} in metadb archive, failed to deserialize as JSON: {}", i, e);
-						continue;
-					},
-				}
-			};
-			
-			if let Some(schema) = &compiled_schema {
-				if !schema.is_valid(&json) {
-					log::warn!("Couldn't process entry {} in metadb, it does not match the schema", i);
-					continue;
-				}
+			let path = entry.name().to_string();
+			let mut buffer = Vec::<u8>::new();
+			entry.read_to_end(&mut buffer)?;
+			self.process_entry(path, buffer)?;
+		}
+		Ok(())
+	}
+
+	fn read_directory(&mut self, directory: &std::path::Path) -> crate::Result<()> {
+		log::trace!("Reading directory {}", directory.display());
+		for entry in walkdir::WalkDir::new(directory).sort_by_file_name() {
+			let entry = entry.map_err(std::io::Error::from)?;
+
+			if !entry.file_type().is_file() {
+				continue;
 			}
 
-			{
-				let ckan : Package = match Package::read_from_json(json) {
-					Ok(v) => v,
-					Err(e) => {
-						log::warn!("Couldn't process entry {} in metadb, failed to create package from JSON: {}", i, e);
-						continue;
-					},
-				};
-				packages.insert(ckan);
+			let path = pathdiff::diff_paths(entry.path(), directory).expect("entry should be inside the directory.");
+			let buffer = std::fs::read(entry.path())?;
+			self.process_entry(path.to_string_lossy().replace('\\', "/"), buffer)?;
+		}
+		Ok(())
+	}
+
+	/// Processes a single file from a repository, only `builds.json` and `.ckan` files are read.
+	///
+	/// Changed `.ckan` files are queued and parsed in batches by [`parse_pending_entries()`](RepositoryUpdate::parse_pending_entries()).
+	fn process_entry(&mut self, path: String, buffer: Vec<u8>) -> crate::Result<()> {
+		self.entries_read += 1;
+		if let Some(progress) = &self.progress {
+			let _ = progress.send(GenerationProgress::Processed { repository: self.repository.name.clone(), entries: self.entries_read });
+		}
+
+		if is_builds_file(&path) {
+			log::trace!("Processing builds.json");
+			self.builds.update_from_json(&buffer)?;
+			return Ok(())
+		}
+
+		if !path.to_lowercase().ends_with(".ckan") {
+			return Ok(())
+		}
+
+		let hash = sha256::digest(buffer.as_slice());
+		if let Some(previous) = self.previous_entries.remove(&path) {
+			if previous.hash == hash {
+				self.entries.insert(path, previous);
+				return Ok(())
+			}
+			if let Some(identifier) = previous.identifier {
+				self.package_changes.push(PackageChange::Removed(identifier));
 			}
 		}
 
-		assert!(!builds.is_empty(), "builds.json was not processed.");
+		self.changed = true;
+		self.pending.push(PendingEntry { path, hash, buffer });
+		if self.pending.len() >= PARSE_BATCH_SIZE {
+			self.parse_pending_entries();
+		}
+		Ok(())
+	}
+
+	/// Parses the queued entries across all cores then queues them to be added to the DB in the order they were read.
+	fn parse_pending_entries(&mut self) {
+		if self.pending.is_empty() {
+			return
+		}
+
+		let pending = std::mem::take(&mut self.pending);
+		let results = read_entries(&self.repository, &pending, self.schema.as_ref());
 
-		Ok(Self { packages, builds })
+		for (entry, result) in pending.into_iter().zip(results) {
+			let (identifier, rejection) = match result {
+				Ok(package) => {
+					let id = package.identifier.clone();
+					self.package_changes.push(PackageChange::Added(Box::new(package)));
+					(Some(id), None)
+				},
+				Err(rejection) => {
+					log::warn!("Couldn't process {}", rejection);
+					(None, Some(rejection))
+				},
+			};
+			self.entries.insert(entry.path, EntryInfo { hash: entry.hash, identifier, rejection });
+		}
 	}
 }
 
+/// Reads entries split evenly between threads.
+///
+/// The results are in the same order as `entries` so the DB is the same regardless of the number of threads.
+fn read_entries(repository: &Repository, entries: &[PendingEntry], schema: Option<&jsonschema::JSONSchema>) -> Vec<Result<Package, RejectedEntry>> {
+	let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+	let chunk_size = entries.len().div_ceil(threads).max(1);
+
+	std::thread::scope(|scope| {
+		let handles = entries.chunks(chunk_size).map(|chunk| {
+			scope.spawn(move || {
+				chunk.iter().map(|e| MetaDB::read_entry(repository, &e.path, &e.buffer, schema)).collect::<Vec<_>>()
+			})
+		}).collect::<Vec<_>>();
+
+		handles.into_iter().flat_map(|h| h.join().expect("entry parsing thread should not panic.")).collect()
+	})
+}
+
+/// Checks if an archive path is the `builds.json` at the root of the repository.
+///
+/// Archives downloaded from a git host usually place the repository inside a single top level directory.
+fn is_builds_file(path: &str) -> bool {
+	let path = std::path::Path::new(path);
+	path.file_name().is_some_and(|f| f == "builds.json") && path.components().count() <= 2
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::test_fixtures::*;
+	use serde_json::json;
 
 	#[test]
 	fn ckan_json_schema_compiles() {
@@ -116,9 +663,214 @@ mod tests {
 		).expect("schema isn't invalid");
 	}
 
+	#[test]
+	fn update_from_archive_patches_changed_entries() {
+		let first = build_archive(&[
+			("CKAN-meta-master/A/A-1.0.ckan", ckan_json("A", "1.0", json!({})).to_string()),
+			("CKAN-meta-master/B/B-1.0.ckan", ckan_json("B", "1.0", json!({})).to_string()),
+		]);
+		let (mut db, _) = MetaDB::generate_from_archive(&mut tar::Archive::new(first.as_slice()), false).unwrap();
+		assert_eq!(db.get_packages().len(), 2);
+
+		assert!(!db.update_from_archive(&Repository::default(), &mut tar::Archive::new(first.as_slice()), false).unwrap());
+
+		let second = build_archive(&[
+			("CKAN-meta-master/A/A-1.0.ckan", ckan_json("A", "1.1", json!({})).to_string()),
+			("CKAN-meta-master/C/C-1.0.ckan", ckan_json("C", "1.0", json!({})).to_string()),
+		]);
+		assert!(db.update_from_archive(&Repository::default(), &mut tar::Archive::new(second.as_slice()), false).unwrap());
+		assert_eq!(db.get_packages().len(), 2);
+		assert!(db.get_from_unique_id(id("A", "1.0")).is_none());
+		assert!(db.get_from_unique_id(id("A", "1.1")).is_some());
+		assert!(db.get_from_unique_id(id("B", "1.0")).is_none());
+		assert!(db.get_from_unique_id(id("C", "1.0")).is_some());
+	}
+
+	#[test]
+	fn higher_priority_repository_overrides_package() {
+		let official = Repository::default();
+		let private = Repository::new("private", "https://example.com/private.tar.gz", 10);
+
+		let private_package = ckan_json("A", "1.0", json!({ "abstract": "A private package." })).to_string();
+
+		let mut db = MetaDB::default();
+		db.update_from_archive(&official, &mut tar::Archive::new(build_archive(&[("CKAN-meta-master/A/A-1.0.ckan", ckan_json("A", "1.0", json!({})).to_string())]).as_slice()), false).unwrap();
+		db.update_from_archive(&private, &mut tar::Archive::new(build_archive(&[("private-main/A-1.0.ckan", private_package)]).as_slice()), false).unwrap();
+
+		assert_eq!(db.get_packages().len(), 1);
+		assert_eq!(db.get_from_unique_id(id("A", "1.0")).unwrap().blurb, "A private package.");
+		assert_eq!(db.get_package_repository(id("A", "1.0")), Some("private"));
+
+		/* Removing the private package should reveal the official one again */
+		db.update_from_archive(&private, &mut tar::Archive::new(build_archive(&[]).as_slice()), false).unwrap();
+		assert_eq!(db.get_from_unique_id(id("A", "1.0")).unwrap().blurb, "A test package.");
+		assert_eq!(db.get_package_repository(id("A", "1.0")), Some("CKAN-meta"));
+	}
+
+	#[test]
+	fn failed_update_keeps_entries_tracked() {
+		let first = build_archive(&[
+			("CKAN-meta-master/ModA/ModA-1.0.ckan", ckan_json("ModA", "1.0", json!({})).to_string()),
+			("CKAN-meta-master/ModB/ModB-1.0.ckan", ckan_json("ModB", "1.0", json!({})).to_string()),
+		]);
+		let (mut db, _) = MetaDB::generate_from_archive(&mut tar::Archive::new(first.as_slice()), false).unwrap();
+
+		/* Cut the archive off before its last entry can be read */
+		let second = build_archive(&[
+			("CKAN-meta-master/ModA/ModA-1.0.ckan", ckan_json("ModA", "1.1", json!({})).to_string()),
+			("CKAN-meta-master/ModB/ModB-1.0.ckan", ckan_json("ModB", "1.0", json!({})).to_string()),
+		]);
+		let truncated = &second[..512 * 4 + 100];
+		assert!(db.update_from_archive(&Repository::default(), &mut tar::Archive::new(truncated), false).is_err());
+
+		let third = build_archive(&[
+			("CKAN-meta-master/ModA/ModA-1.0.ckan", ckan_json("ModA", "1.1", json!({})).to_string()),
+		]);
+		db.update_from_archive(&Repository::default(), &mut tar::Archive::new(third.as_slice()), false).unwrap();
+		assert_eq!(db.get_packages().len(), 1);
+		assert!(db.get_from_unique_id(id("ModA", "1.1")).is_some());
+		assert!(db.get_from_unique_id(id("ModB", "1.0")).is_none());
+	}
+
+	#[test]
+	fn rejected_entries_are_reported() {
+		let archive = build_archive(&[
+			("CKAN-meta-master/ModA/ModA-1.0.ckan", ckan_json("ModA", "1.0", json!({})).to_string()),
+			("CKAN-meta-master/B/B-1.0.ckan", "{ not json".to_string()),
+			("CKAN-meta-master/ModC/ModC-1.0.ckan", ckan_json("ModC", "1.0", json!({ "license": null })).to_string()),
+		]);
+		let (mut db, report) = MetaDB::generate_from_archive(&mut tar::Archive::new(archive.as_slice()), true).unwrap();
+		assert_eq!(db.get_packages().len(), 1);
+		assert_eq!(report.rejected.len(), 2);
+		assert_eq!(report.rejected[0].path, "CKAN-meta-master/B/B-1.0.ckan");
+		assert_eq!(report.rejected[0].stage, RejectionStage::Json);
+		assert_eq!(report.rejected[0].identifier, None);
+		assert_eq!(report.rejected[1].stage, RejectionStage::Schema);
+		assert_eq!(report.rejected[1].identifier.as_deref(), Some("ModC"));
+
+		/* Unchanged entries skipped by an update are still reported */
+		db.update_from_archive(&Repository::default(), &mut tar::Archive::new(archive.as_slice()), true).unwrap();
+		assert_eq!(db.get_generation_report().rejected, report.rejected);
+	}
+
+	#[test]
+	fn batched_parsing_keeps_entry_order() {
+		let count = PARSE_BATCH_SIZE * 2 + 10;
+		let mut entries = (0..count).map(|i| (format!("CKAN-meta-master/Mod{i:04}/Mod{i:04}-1.0.ckan"), ckan_json(&format!("Mod{i:04}"), "1.0", json!({})).to_string())).collect::<Vec<_>>();
+		entries[PARSE_BATCH_SIZE + 1].1 = "{ not json".to_string();
+		entries[3].1 = "{ not json".to_string();
+		let entries = entries.iter().map(|(p, d)| (p.as_str(), d.clone())).collect::<Vec<_>>();
+
+		let (db, report) = MetaDB::generate_from_archive(&mut tar::Archive::new(build_archive(&entries).as_slice()), true).unwrap();
+		assert_eq!(db.get_packages().len(), count - 2);
+		assert_eq!(report.rejected.iter().map(|r| r.path.as_str()).collect::<Vec<_>>(), vec![entries[3].0, entries[PARSE_BATCH_SIZE + 1].0]);
+	}
+
+	#[tokio::test]
+	async fn archive_is_read_while_streaming() {
+		let mut gz = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+		std::io::Write::write_all(&mut gz, &build_archive(&[("CKAN-meta-master/ModA/ModA-1.0.ckan", ckan_json("ModA", "1.0", json!({})).to_string())])).unwrap();
+		let data = gz.finish().unwrap();
+
+		let (sender, receiver) = tokio::sync::mpsc::channel(ARCHIVE_CHUNK_BUFFER);
+		let (progress, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel();
+		let mut db = MetaDB::default();
+		let mut update = db.begin_repository_update(&Repository::default(), true, Some(progress));
+		let processing = tokio::task::spawn_blocking(move || {
+			let reader = ChannelReader { receiver, chunk: Vec::new(), position: 0 };
+			let result = update.read_tar(&mut tar::Archive::new(flate2::read::GzDecoder::new(reader)));
+			(update, result)
+		});
+
+		for chunk in data.chunks(7) {
+			/* The reader stops at the end of the tar and may leave the gzip trailer unread */
+			if sender.send(Ok(chunk.to_vec())).await.is_err() {
+				break;
+			}
+		}
+		drop(sender);
+
+		let (update, result) = processing.await.unwrap();
+		assert!(db.finish_repository_update(update, result).unwrap());
+		assert!(db.get_from_unique_id(id("ModA", "1.0")).is_some());
+		assert!(!db.get_game_builds().is_empty());
+
+		let mut last = None;
+		while let Ok(p) = progress_receiver.try_recv() {
+			last = Some(p);
+		}
+		assert_eq!(last, Some(GenerationProgress::Processed { repository: "CKAN-meta".to_string(), entries: 2 }));
+	}
+
+	#[tokio::test]
+	async fn dropped_update_keeps_db() {
+		let archive = build_archive(&[("CKAN-meta-master/ModA/ModA-1.0.ckan", ckan_json("ModA", "1.0", json!({})).to_string())]);
+		let (mut db, _) = MetaDB::generate_from_archive(&mut tar::Archive::new(archive.as_slice()), false).unwrap();
+
+		/* Send the start of an archive then stall so the update is dropped while reading */
+		let mut gz = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+		std::io::Write::write_all(&mut gz, &build_archive(&[("CKAN-meta-master/ModB/ModB-1.0.ckan", ckan_json("ModB", "1.0", json!({})).to_string())])).unwrap();
+		let data = gz.finish().unwrap();
+		let address = serve("127.0.0.1", move |request, mut stream| {
+			let data = data.clone();
+			async move {
+				use tokio::io::AsyncWriteExt;
+				assert_eq!(request.path, "/meta.tar.gz");
+				let _ = stream.write_all(&response("200 OK", &[("Content-Length", data.len().to_string())], &data[..data.len() / 2])).await;
+				std::future::pending::<()>().await;
+			}
+		}).await;
+
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_repositories(vec![Repository::new("CKAN-meta", format!("http://{address}/meta.tar.gz"), 0)]));
+		assert!(tokio::time::timeout(std::time::Duration::from_millis(500), db.update_latest(&config)).await.is_err());
+
+		assert_eq!(db.get_packages().len(), 1);
+		assert!(db.get_from_unique_id(id("ModA", "1.0")).is_some());
+		assert_eq!(db.repositories.get("CKAN-meta").unwrap().entries.len(), 1);
+	}
+
+	#[test]
+	fn generate_from_local_sources() {
+		let dir = tempfile::tempdir().unwrap();
+
+		/* A folder of loose files */
+		let loose = dir.path().join("loose");
+		std::fs::create_dir_all(loose.join("ModB")).unwrap();
+		std::fs::write(loose.join("ModA-1.0.ckan"), ckan_json("ModA", "1.0", json!({})).to_string()).unwrap();
+		std::fs::write(loose.join("ModB").join("ModB-1.0.ckan"), ckan_json("ModB", "1.0", json!({})).to_string()).unwrap();
+		std::fs::write(loose.join("README.md"), "Not a package.").unwrap();
+		let (db, _) = MetaDB::generate_from_path(&loose, true).unwrap();
+		assert_eq!(db.get_packages().len(), 2);
+
+		/* A zip archive */
+		let zip_path = dir.path().join("meta.zip");
+		{
+			let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+			zip.start_file("CKAN-meta-master/ModA/ModA-1.0.ckan", Default::default()).unwrap();
+			std::io::Write::write_all(&mut zip, ckan_json("ModA", "1.0", json!({})).to_string().as_bytes()).unwrap();
+			zip.finish().unwrap();
+		}
+		let (db, _) = MetaDB::generate_from_path(&zip_path, true).unwrap();
+		assert!(db.get_from_unique_id(id("ModA", "1.0")).is_some());
+
+		/* A compressed tarball */
+		let tar_path = dir.path().join("meta.tar.gz");
+		{
+			let mut gz = flate2::write::GzEncoder::new(std::fs::File::create(&tar_path).unwrap(), Default::default());
+			std::io::Write::write_all(&mut gz, &build_archive(&[("CKAN-meta-master/ModC/ModC-1.0.ckan", ckan_json("ModC", "1.0", json!({})).to_string())])).unwrap();
+			gz.finish().unwrap();
+		}
+		let (db, _) = MetaDB::generate_from_path(&tar_path, true).unwrap();
+		assert!(db.get_from_unique_id(id("ModC", "1.0")).is_some());
+		assert!(!db.get_game_builds().is_empty());
+
+		assert!(MetaDB::generate_from_path(dir.path().join("meta.rar"), true).is_err());
+	}
+
 	#[tokio::test]
 	async fn get_lastest_db_archive() {
-		let a = get_latest_archive().await.expect("failed to download archive.");
+		let a = request_archive(&Repository::default(), &Default::default()).await.expect("failed to download archive.").expect("archive should be downloaded without a previous etag.").bytes().await.expect("failed to download archive.");
 		if a.is_empty() {
 			panic!("data is empty.")
 		}
@@ -126,4 +878,4 @@ mod tests {
 			panic!("data seems too small <2mb.")
 		}
 	}
-}
\ No newline at end of file
+}
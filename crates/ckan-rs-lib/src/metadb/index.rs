@@ -0,0 +1,273 @@
+//! Lookup tables kept alongside the packages to avoid scanning the whole DB.
+//!
+//! Every change to the DB's packages must go through [`MetaDB::put_package()`] or [`MetaDB::take_package()`]
+//! to keep the indexes up to date.
+
+use super::*;
+use package::{KspVersionReal, PackageDescriptor, PackageVersionBounds, RelationshipKind, ReleaseStatus};
+
+/// Indexes derived from the packages in a [`MetaDB`].
+///
+/// These aren't saved with the DB and are instead rebuilt when loaded.
+#[derive(Debug, Default)]
+pub(super) struct Indexes {
+	/// Versions of each identifier sorted from oldest to newest.
+	versions: HashMap<String, Vec<PackageVersion>>,
+	/// Identifiers with at least one version providing a name, a package always provides its own identifier.
+	providers: HashMap<String, HashSet<String>>,
+	/// Packages with a relationship of any kind using a name.
+	relationships: HashMap<String, HashSet<PackageIdentifier>>,
+}
+
+impl MetaDB {
+	/// Gets every identifier with at least one package in the DB, in no particular order.
+	pub fn get_identifiers(&self) -> impl Iterator<Item = &String> {
+		self.indexes.versions.keys()
+	}
+
+	/// Gets every version of `identifier` sorted from oldest to newest.
+	pub fn get_versions(&self, identifier: &str) -> &[PackageVersion] {
+		self.indexes.versions.get(identifier).map(|v| v.as_slice()).unwrap_or_default()
+	}
+
+	/// Gets every package with `identifier` sorted from oldest to newest version.
+	pub fn get_packages_with_identifier(&self, identifier: &str) -> impl DoubleEndedIterator<Item = &Package> + '_ {
+		/* The indexed key is used so the iterator only borrows the DB */
+		self.indexes.versions.get_key_value(identifier).into_iter().flat_map(move |(identifier, versions)| {
+			versions.iter().map(move |version| {
+				self.packages.get(&PackageIdentifier { identifier: identifier.clone(), version: version.clone() })
+					.expect("indexed package should be present.")
+			})
+		})
+	}
+
+	/// Gets every package with `identifier` sorted from newest to oldest version.
+	pub fn get_version_history(&self, identifier: &str) -> impl Iterator<Item = &Package> + '_ {
+		self.get_packages_with_identifier(identifier).rev()
+	}
+
+	/// Gets the newest package with `identifier`.
+	pub fn get_latest(&self, identifier: &str) -> Option<&Package> {
+		self.get_packages_with_identifier(identifier).next_back()
+	}
+
+	/// Gets the newest package with `identifier` which can be installed.
+	///
+	/// # Parameters
+	/// - `ksp_versions` - The package must be compatible with at least one of these versions, when empty any game version is accepted.
+	/// - `bounds` - The range of package versions accepted.
+	/// - `max_release_status` - The least stable release accepted, for example [`Testing`](ReleaseStatus::Testing) accepts testing and stable releases.
+	pub fn get_latest_compatible(&self, identifier: &str, ksp_versions: &[KspVersionReal], bounds: &PackageVersionBounds, max_release_status: ReleaseStatus) -> Option<&Package> {
+		self.get_version_history(identifier).find(|p| {
+			p.release_status <= max_release_status
+			&& bounds.is_version_within(&p.identifier.version)
+			&& (ksp_versions.is_empty() || ksp_versions.iter().any(|v| p.ksp_version.is_version_compatible(v, p.ksp_version_strict)))
+		})
+	}
+
+	/// Gets the newest package matching the [`replaced_by`](Package::replaced_by) descriptor of `package`.
+	///
+	/// Only packages with the replacement's identifier are considered, not those providing it.
+	pub fn get_replacement(&self, package: &Package) -> Option<&Package> {
+		let descriptor = package.replaced_by.as_ref()?;
+		self.get_version_history(&descriptor.name).find(|p| descriptor.version.is_version_within(&p.identifier.version))
+	}
+
+	/// Gets the identifiers which have at least one version providing `name`, including `name` itself if it is a real package.
+	pub fn get_identifiers_providing(&self, name: &str) -> impl Iterator<Item = &String> {
+		self.indexes.providers.get(name).into_iter().flatten()
+	}
+
+	/// Gets the packages providing a [`PackageDescriptor`] grouped by their identifier.
+	///
+	/// Equivalent to [`GetProvidersExt::get_packages_providing()`] but uses the DB's indexes instead of checking every package.
+	pub fn get_packages_providing(&self, descriptor: &PackageDescriptor) -> HashMap<String, Vec<&Package>> {
+		let mut map = HashMap::<String, Vec<&Package>>::new();
+		for identifier in self.get_identifiers_providing(&descriptor.name) {
+			for package in self.get_packages_with_identifier(identifier) {
+				if package::does_package_provide_descriptor(package, descriptor) {
+					map.entry(identifier.clone()).or_default().push(package);
+				}
+			}
+		}
+		map
+	}
+
+	/// Gets the packages with a `kind` relationship fulfilled by `package`, either directly or through its `provides`.
+	///
+	/// Other versions of `package` are excluded, sorted by identifier and version.
+	pub fn get_reverse_relationships(&self, package: &Package, kind: RelationshipKind) -> Vec<&Package> {
+		let mut packages = package.provides.iter().chain(std::iter::once(&package.identifier.identifier))
+			.filter_map(|name| self.indexes.relationships.get(name))
+			.flatten()
+			.filter(|id| id.identifier != package.identifier.identifier)
+			.collect::<HashSet<_>>()
+			.into_iter()
+			.map(|id| self.packages.get(id).expect("indexed package should be present."))
+			.filter(|p| p.get_relationships(kind).iter().any(|r| package::does_package_fulfill_relationship(package, r)))
+			.collect::<Vec<_>>();
+		packages.sort();
+		packages
+	}
+
+	/// Gets the packages with a `kind` relationship fulfilled by any version of `identifier`.
+	///
+	/// See [`get_reverse_relationships()`](MetaDB::get_reverse_relationships()).
+	pub fn get_reverse_relationships_of_identifier(&self, identifier: &str, kind: RelationshipKind) -> Vec<&Package> {
+		let mut packages = self.get_packages_with_identifier(identifier)
+			.flat_map(|p| self.get_reverse_relationships(p, kind))
+			.collect::<Vec<_>>();
+		packages.sort();
+		packages.dedup();
+		packages
+	}
+
+	/// Adds or replaces a package and updates the indexes.
+	pub(super) fn put_package(&mut self, package: Package) -> Option<Package> {
+		let previous = self.take_package(&package.identifier);
+
+		let id = &package.identifier;
+		let versions = self.indexes.versions.entry(id.identifier.clone()).or_default();
+		if let Err(i) = versions.binary_search(&id.version) {
+			versions.insert(i, id.version.clone());
+		}
+		for name in package.provides.iter().chain(std::iter::once(&id.identifier)) {
+			self.indexes.providers.entry(name.clone()).or_default().insert(id.identifier.clone());
+		}
+		for name in package.relationship_names() {
+			self.indexes.relationships.entry(name.clone()).or_default().insert(id.clone());
+		}
+
+		self.packages.insert(package);
+		previous
+	}
+
+	/// Removes a package and updates the indexes.
+	pub(super) fn take_package(&mut self, id: &PackageIdentifier) -> Option<Package> {
+		let package = self.packages.take(id)?;
+
+		if let Some(versions) = self.indexes.versions.get_mut(&id.identifier) {
+			if let Ok(i) = versions.binary_search(&id.version) {
+				versions.remove(i);
+			}
+			if versions.is_empty() {
+				self.indexes.versions.remove(&id.identifier);
+			}
+		}
+
+		for name in package.provides.iter().chain(std::iter::once(&id.identifier)) {
+			let still_provided = self.get_packages_with_identifier(&id.identifier).any(|p| &p.identifier.identifier == name || p.provides.contains(name));
+			if still_provided {
+				continue;
+			}
+			if let Some(providers) = self.indexes.providers.get_mut(name) {
+				providers.remove(&id.identifier);
+				if providers.is_empty() {
+					self.indexes.providers.remove(name);
+				}
+			}
+		}
+
+		for name in package.relationship_names() {
+			if let Some(packages) = self.indexes.relationships.get_mut(name) {
+				packages.remove(id);
+				if packages.is_empty() {
+					self.indexes.relationships.remove(name);
+				}
+			}
+		}
+
+		Some(package)
+	}
+
+	/// Recreates the indexes from the packages, used after loading the DB.
+	pub(super) fn rebuild_indexes(&mut self) {
+		self.indexes = Default::default();
+		for package in &self.packages {
+			let id = &package.identifier;
+			self.indexes.versions.entry(id.identifier.clone()).or_default().push(id.version.clone());
+			for name in package.provides.iter().chain(std::iter::once(&id.identifier)) {
+				self.indexes.providers.entry(name.clone()).or_default().insert(id.identifier.clone());
+			}
+			for name in package.relationship_names() {
+				self.indexes.relationships.entry(name.clone()).or_default().insert(id.clone());
+			}
+		}
+		for versions in self.indexes.versions.values_mut() {
+			versions.sort();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use package::*;
+	use crate::test_fixtures::package;
+	use serde_json::json;
+
+	#[test]
+	fn indexes_track_versions_and_providers() {
+		let mut db = MetaDB::default();
+		db.put_package(package("Scatterer", "0.0.2", json!({})));
+		db.put_package(package("Scatterer", "0.0.10", json!({})));
+		db.put_package(package("EVE", "1.0", json!({ "provides": ["EnvironmentalVisualEnhancements-Config"] })));
+		db.put_package(package("EVE-Stock", "1.0", json!({ "provides": ["EnvironmentalVisualEnhancements-Config"] })));
+
+		let versions = db.get_packages_with_identifier("Scatterer").map(|p| p.identifier.version.clone()).collect::<Vec<_>>();
+		assert_eq!(versions, vec![PackageVersion::new("0.0.2").unwrap(), PackageVersion::new("0.0.10").unwrap()]);
+
+		let providers = db.get_packages_providing(&PackageDescriptor::new("EnvironmentalVisualEnhancements-Config".to_string(), VersionBounds::Any));
+		assert_eq!(providers.len(), 2);
+		assert_eq!(db.get_packages_providing(&PackageDescriptor::new("Scatterer".to_string(), VersionBounds::Any))["Scatterer"].len(), 2);
+
+		db.take_package(&PackageIdentifier { identifier: "EVE-Stock".to_string(), version: PackageVersion::new("1.0").unwrap() });
+		assert_eq!(db.get_identifiers_providing("EnvironmentalVisualEnhancements-Config").collect::<Vec<_>>(), vec!["EVE"]);
+		assert!(db.get_versions("EVE-Stock").is_empty());
+
+		let mut rebuilt = MetaDB { packages: std::mem::take(&mut db.packages), ..Default::default() };
+		rebuilt.rebuild_indexes();
+		assert_eq!(rebuilt.get_versions("Scatterer"), versions.as_slice());
+	}
+
+	#[test]
+	fn latest_compatible_respects_constraints() {
+		let mut db = MetaDB::default();
+		db.put_package(package("Parallax", "1.0", json!({ "ksp_version": "1.11" })));
+		db.put_package(package("Parallax", "2.0", json!({ "ksp_version": "1.12" })));
+		db.put_package(package("Parallax", "2.1", json!({ "ksp_version": "1.12", "release_status": "testing" })));
+		db.put_package(package("Parallax", "3.0", json!({ "ksp_version": "1.13" })));
+
+		let versions = |v: &[&str]| v.iter().map(|v| PackageVersion::new(v).unwrap()).collect::<Vec<_>>();
+		assert_eq!(db.get_version_history("Parallax").map(|p| p.identifier.version.clone()).collect::<Vec<_>>(), versions(&["3.0", "2.1", "2.0", "1.0"]));
+		assert_eq!(db.get_latest("Parallax").unwrap().identifier.version, versions(&["3.0"])[0]);
+
+		let ksp = [KspVersionReal::new("1.12.5").unwrap()];
+		let latest = |bounds: &PackageVersionBounds, status| db.get_latest_compatible("Parallax", &ksp, bounds, status).map(|p| p.identifier.version.clone());
+		assert_eq!(latest(&VersionBounds::Any, ReleaseStatus::Stable), Some(versions(&["2.0"])[0].clone()));
+		assert_eq!(latest(&VersionBounds::Any, ReleaseStatus::Testing), Some(versions(&["2.1"])[0].clone()));
+		assert_eq!(latest(&VersionBounds::MaxOnly(versions(&["1.5"])[0].clone()), ReleaseStatus::Development), None);
+		assert!(db.get_latest_compatible("Parallax", &[], &VersionBounds::Any, ReleaseStatus::Stable).is_some_and(|p| p.identifier.version == versions(&["3.0"])[0]));
+	}
+
+	#[test]
+	fn reverse_relationships_respect_provides_and_bounds() {
+		let mut db = MetaDB::default();
+		db.put_package(package("ModuleManager", "4.2.2", json!({})));
+		db.put_package(package("Kopernicus", "1.0", json!({ "provides": ["Kopernicus-Core"] })));
+		db.put_package(package("PlanetPack", "1.0", json!({ "depends": [{ "name": "ModuleManager", "min_version": "4.0" }, { "name": "Kopernicus-Core" }] })));
+		db.put_package(package("OldPack", "1.0", json!({ "depends": [{ "name": "ModuleManager", "max_version": "3.0" }] })));
+		db.put_package(package("Tweaks", "1.0", json!({ "suggests": [{ "any_of": [{ "name": "Kopernicus" }, { "name": "Other" }] }] })));
+		db.put_package(package("Kopernicus", "0.9", json!({ "conflicts": [{ "name": "Kopernicus" }] })));
+
+		let ids = |packages: Vec<&Package>| packages.iter().map(|p| p.identifier.identifier.clone()).collect::<Vec<_>>();
+		let module_manager = db.get_latest("ModuleManager").unwrap();
+		assert_eq!(ids(db.get_reverse_relationships(module_manager, RelationshipKind::Depends)), vec!["PlanetPack"]);
+		assert_eq!(ids(db.get_reverse_relationships_of_identifier("Kopernicus", RelationshipKind::Depends)), vec!["PlanetPack"]);
+		assert_eq!(ids(db.get_reverse_relationships_of_identifier("Kopernicus", RelationshipKind::Suggests)), vec!["Tweaks"]);
+		assert!(db.get_reverse_relationships_of_identifier("Kopernicus", RelationshipKind::Conflicts).is_empty());
+
+		db.take_package(&PackageIdentifier { identifier: "PlanetPack".to_string(), version: PackageVersion::new("1.0").unwrap() });
+		assert!(db.get_reverse_relationships(db.get_latest("ModuleManager").unwrap(), RelationshipKind::Depends).is_empty());
+	}
+}
@@ -1,6 +1,6 @@
 //! Various types associated with packages.
 
-use std::{collections::{HashMap, HashSet}};
+use std::collections::HashSet;
 use serde::*;
 
 /* CKAN */
@@ -9,10 +9,10 @@ use serde::*;
 /// 
 /// We use the term "Package" instead of "Module" due to the overlap with rust's keywords.
 /* NOTE: We don't use serde's deserialize to import the .ckan files because it's way to involved and limited. use `read_from_json` associated function instead. */
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Package {
 	/* Required Fields */
-	pub spec_version: String,
+	pub spec_version: SpecVersion,
 	pub identifier: relationship::PackageIdentifier,
 	pub name: String,
 	/// Rust friendly alias for `abstract`.
@@ -27,26 +27,32 @@ pub struct Package {
 	/* Optional Fields */
 	pub install: Vec<install::InstallDirective>,
 	pub description: Option<String>,
+	/// Notes for maintainers of the metadata, not intended to be shown to users.
+	pub comment: Option<String>,
 	pub release_status: ReleaseStatus,
 	pub ksp_version: ksp_version::KspVersionBounds,
 	pub ksp_version_strict: bool,
 	pub tags: Option<Vec<String>>,
 	pub localizations: Option<Vec<String>>,
 	pub download_size: Option<u64>, /* *Really* Don't use anything lower than 64 here, 32 is only 4gb max size */
-	pub download_hash_sha1: Option<Vec<u8>>,
-	pub download_hash_sha256: Option<Vec<u8>>,
+	pub download_hash_sha1: Option<DownloadHash>,
+	pub download_hash_sha256: Option<DownloadHash>,
 	pub download_content_type: Option<String>,
 	pub install_size: Option<u64>,
-	pub release_date: Option<String>,
+	/// Dates without a time are treated as midnight UTC.
+	pub release_date: Option<chrono::DateTime<chrono::Utc>>,
 	pub depends: Vec<Relationship>,
 	pub recommends: Vec<Relationship>,
 	pub suggests: Vec<Relationship>,
 	pub supports: Vec<Relationship>,
 	pub conflicts: Vec<Relationship>,
+	/// The package which should be installed instead of this one, see [`MetaDB::get_replacement()`](crate::MetaDB::get_replacement()).
 	pub replaced_by: Option<PackageDescriptor>,
 	pub kind: Kind,
 	pub provides: HashSet<String>,
-	pub resources: HashMap<String, String>,
+	pub resources: Resources,
+	/// Fields prefixed with `x_`.
+	pub extensions: Extensions,
 }
 
 impl std::hash::Hash for Package {
@@ -75,6 +81,13 @@ impl std::cmp::PartialEq for Package {
 
 impl std::cmp::Eq for Package {}
 
+/* NOTE: `Hash` and `Eq` only consider the identifier so a package can be looked up by it in hashed collections. */
+impl std::borrow::Borrow<PackageIdentifier> for Package {
+	fn borrow(&self) -> &PackageIdentifier {
+		&self.identifier
+	}
+}
+
 impl AsRef<PackageIdentifier> for Package {
 	fn as_ref(&self) -> &PackageIdentifier {
 		&self.identifier
@@ -82,6 +95,30 @@ impl AsRef<PackageIdentifier> for Package {
 }
 
 impl Package {
+	/// Gets the relationships of a kind.
+	pub fn get_relationships(&self, kind: RelationshipKind) -> &Vec<Relationship> {
+		match kind {
+			RelationshipKind::Depends => &self.depends,
+			RelationshipKind::Recommends => &self.recommends,
+			RelationshipKind::Suggests => &self.suggests,
+			RelationshipKind::Supports => &self.supports,
+			RelationshipKind::Conflicts => &self.conflicts,
+		}
+	}
+
+	/// Iterates over the names used in every relationship of the package.
+	pub fn relationship_names(&self) -> impl Iterator<Item = &String> {
+		RelationshipKind::ALL.into_iter()
+			.flat_map(|kind| self.get_relationships(kind))
+			.flat_map(|r| r.as_vec())
+			.map(|d| &d.name)
+	}
+
+	/// Gets the strongest hash of the download, SHA-256 is preferred over SHA-1.
+	pub fn get_download_hash(&self) -> Option<&DownloadHash> {
+		self.download_hash_sha256.as_ref().or(self.download_hash_sha1.as_ref())
+	}
+
 	/// Checks if the given packages conflict with each other.
 	pub fn do_packages_conflict(lhs: &Self, rhs: &Self) -> bool {
 		let mut conflicts = false;
@@ -104,32 +141,58 @@ mod ksp_version;
 pub use ksp_version::KspVersionReal;
 pub use ksp_version::KspVersionBounds;
 
+mod spec_version;
+pub use spec_version::SpecVersion;
+
 mod package_version;
 pub use package_version::PackageVersion;
 pub use package_version::PackageVersionBounds;
 
 pub mod install;
 
+mod download_hash;
+pub use download_hash::DownloadHash;
+pub use download_hash::DownloadHasher;
+pub use download_hash::HashAlgorithm;
+
+mod resources;
+pub use resources::Resources;
+
+mod extensions;
+pub use extensions::Extensions;
 
 mod relationship;
 pub use relationship::PackageIdentifier;
 pub use relationship::PackageDescriptor;
 pub use relationship::Relationship;
+pub use relationship::RelationshipKind;
 pub use relationship::does_package_fulfill_relationship;
 pub use relationship::does_package_provide_descriptor;
 pub use relationship::does_package_match_descriptor;
 
 /// The stability of a package.
-#[derive(Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// 
+/// Ordered from most to least stable.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ReleaseStatus {
+	/// Suitable for general use.
 	#[default] Stable,
+	/// A pre-release for users wanting to test new features, should only be offered when the user opts in.
 	Testing,
+	/// A build which is expected to have bugs or missing features, should only be offered when the user opts in.
 	Development,
 }
 
+impl ReleaseStatus {
+	/// Returns `true` for [`Testing`](ReleaseStatus::Testing) and [`Development`](ReleaseStatus::Development) releases.
+	pub fn is_prerelease(&self) -> bool {
+		*self != ReleaseStatus::Stable
+	}
+}
+
 /* TODO: Move Kind-dependent info (such as download url) into this enum's variants */
 /// The type of a package.
-#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Kind {
 	/// A normal installable module.
 	#[default] Package,
@@ -139,4 +202,8 @@ pub enum Kind {
 	DLC,
 }
 
-mod import;
\ No newline at end of file
+mod authoring;
+pub use authoring::PackageTemplate;
+
+mod import;
+mod export;
\ No newline at end of file
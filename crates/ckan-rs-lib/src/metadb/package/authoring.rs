@@ -0,0 +1,200 @@
+//! Generating `.ckan` metadata from a mod's archive.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use super::*;
+use install::{InstallDirective, SourceDirective};
+
+/// The fields of a package which can't be read from its archive, see [`Package::generate_from_archive()`].
+#[derive(Debug, Clone, Default)]
+pub struct PackageTemplate {
+	pub identifier: String,
+	pub version: String,
+	/// Uses the identifier when empty.
+	pub name: String,
+	/// Rust friendly alias for `abstract`.
+	pub blurb: String,
+	pub author: Vec<String>,
+	pub license: Vec<String>,
+	/// The URL the archive will be published at.
+	pub download: String,
+	pub ksp_version: KspVersionBounds,
+}
+
+/// Guesses the install directives for an archive containing `files`.
+///
+/// In order of preference:
+/// 1. A directory named after the identifier is found and installed to `GameData`, the spec's default.
+/// 1. The contents of a `GameData` directory are installed to `GameData`.
+/// 1. A single top level directory is installed to `GameData`.
+fn infer_install_directives(identifier: &str, files: &[String]) -> crate::Result<Vec<InstallDirective>> {
+	let directories = files.iter()
+		.flat_map(|f| f.match_indices('/').map(|(i, _)| &f[..i]))
+		.collect::<BTreeSet<_>>();
+	let name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+
+	if directories.iter().any(|d| name(d) == identifier) {
+		return Ok(vec![InstallDirective::new(SourceDirective::Find(identifier.to_string()), "GameData".to_string(), Default::default())])
+	}
+
+	if let Some(game_data) = directories.iter().filter(|d| name(d).eq_ignore_ascii_case("GameData")).min_by_key(|d| d.matches('/').count()) {
+		let prefix = format!("{}/", game_data);
+		let children = files.iter()
+			.filter_map(|f| f.strip_prefix(&prefix))
+			.map(|f| f.split('/').next().expect("split should return at least one element."))
+			.collect::<BTreeSet<_>>();
+		if !children.is_empty() {
+			return Ok(children.into_iter()
+				.map(|c| InstallDirective::new(SourceDirective::File(format!("{}{}", prefix, c)), "GameData".to_string(), Default::default()))
+				.collect())
+		}
+	}
+
+	let top_level = files.iter()
+		.map(|f| f.split('/').next().expect("split should return at least one element."))
+		.collect::<BTreeSet<_>>();
+	match top_level.into_iter().collect::<Vec<_>>()[..] {
+		[top] if directories.contains(top) => Ok(vec![InstallDirective::new(SourceDirective::File(top.to_string()), "GameData".to_string(), Default::default())]),
+		_ => Err(crate::Error::Validation("install directives can't be inferred from the archive layout".to_string())),
+	}
+}
+
+/// The oldest spec version supporting everything the generated metadata uses.
+///
+/// Clients refuse packages with a newer spec version than their own so the newest version is never used without reason.
+fn minimum_spec_version(template: &PackageTemplate, install: &[InstallDirective]) -> SpecVersion {
+	let mut version = SpecVersion::from_parts(1, 0);
+	if template.license.len() > 1 {
+		/* Lists of licenses */
+		version = version.max(SpecVersion::from_parts(1, 8));
+	}
+	if install.iter().any(|d| matches!(d.source, SourceDirective::Find(_))) {
+		version = version.max(SpecVersion::from_parts(1, 4));
+	}
+	version
+}
+
+impl Package {
+	/// Creates a package for a mod from its zip archive.
+	///
+	/// The download size, hashes, content type and install size are read from the archive and a default
+	/// install directive is inferred from its layout. The spec version is the oldest one supporting the generated fields.
+	/// The result is validated against the `.ckan` schema.
+	///
+	/// # Errors
+	/// - [`IO`](crate::error::Error::IO) when reading the archive.
+	/// - [`Zip`](crate::error::Error::Zip) when the archive isn't a valid zip.
+	/// - [`Validation`](crate::error::Error::Validation) when the install directives can't be inferred or the package doesn't match the schema.
+	/// - [`Parse`](crate::error::Error::Parse) when a field of the template is invalid, such as the version.
+	pub fn generate_from_archive(template: &PackageTemplate, archive: impl AsRef<Path>) -> crate::Result<Package> {
+		let content = std::fs::read(archive)?;
+
+		let mut zip = zip::ZipArchive::new(std::io::Cursor::new(content.as_slice()))?;
+		let mut files = Vec::new();
+		let mut install_size = 0;
+		for i in 0..zip.len() {
+			let entry = zip.by_index(i)?;
+			if entry.is_file() {
+				install_size += entry.size();
+				files.push(entry.name().to_string());
+			}
+		}
+
+		let install = infer_install_directives(&template.identifier, &files)?;
+
+		let name = if template.name.is_empty() { &template.identifier } else { &template.name };
+		let mut package = Package::read_from_json(serde_json::json!({
+			"spec_version": minimum_spec_version(template, &install).to_json(),
+			"identifier": template.identifier,
+			"version": template.version,
+			"name": name,
+			"abstract": template.blurb,
+			"author": template.author,
+			"license": template.license,
+			"download": template.download,
+		}))?;
+		package.ksp_version = template.ksp_version.clone();
+		package.install = install;
+		package.download_size = Some(content.len() as u64);
+		package.download_hash_sha1 = Some(DownloadHash::compute(HashAlgorithm::Sha1, &content));
+		package.download_hash_sha256 = Some(DownloadHash::compute(HashAlgorithm::Sha256, &content));
+		package.download_content_type = Some("application/zip".to_string());
+		package.install_size = Some(install_size);
+
+		let json = package.write_to_json();
+		if let Err(errors) = crate::metadb::compile_ckan_schema().validate(&json) {
+			return Err(crate::Error::Validation(errors.map(|e| e.to_string()).collect::<Vec<_>>().join("; ")))
+		}
+		Ok(package)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+
+	fn write_zip(path: &Path, files: &[&str]) {
+		let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+		for f in files {
+			zip.start_file(*f, zip::write::FileOptions::default()).unwrap();
+			zip.write_all(b"content").unwrap();
+		}
+		zip.finish().unwrap();
+	}
+
+	#[test]
+	fn install_directives_are_inferred() {
+		let files = |f: &[&str]| f.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+		let sources = |d: Vec<InstallDirective>| d.into_iter().map(|d| d.source).collect::<Vec<_>>();
+
+		let d = infer_install_directives("Example", &files(&["Extras/Readme.txt", "GameData/Example/Plugins/Example.dll"])).unwrap();
+		assert_eq!(sources(d), vec![SourceDirective::Find("Example".to_string())]);
+
+		let d = infer_install_directives("Example", &files(&["Pack/GameData/ExampleParts/part.cfg", "Pack/GameData/ModuleManager.dll"])).unwrap();
+		assert_eq!(sources(d), vec![SourceDirective::File("Pack/GameData/ExampleParts".to_string()), SourceDirective::File("Pack/GameData/ModuleManager.dll".to_string())]);
+
+		let d = infer_install_directives("Example", &files(&["ExampleMod/part.cfg", "ExampleMod/Textures/a.dds"])).unwrap();
+		assert_eq!(sources(d), vec![SourceDirective::File("ExampleMod".to_string())]);
+
+		assert!(infer_install_directives("Example", &files(&["a.cfg", "b.cfg"])).is_err());
+	}
+
+	#[test]
+	fn generate_from_zip() {
+		let dir = tempfile::tempdir().unwrap();
+		let archive = dir.path().join("Example.zip");
+		write_zip(&archive, &["GameData/Example/Example.dll", "GameData/Example/Parts/part.cfg"]);
+
+		let template = PackageTemplate {
+			identifier: "Example".to_string(),
+			version: "1.0".to_string(),
+			blurb: "An example mod.".to_string(),
+			author: vec!["tester".to_string()],
+			license: vec!["MIT".to_string()],
+			download: "https://example.com/Example.zip".to_string(),
+			..Default::default()
+		};
+		let package = Package::generate_from_archive(&template, &archive).unwrap();
+		let content = std::fs::read(&archive).unwrap();
+		assert_eq!(package.name, "Example");
+		assert_eq!(package.download_size, Some(content.len() as u64));
+		assert!(package.download_hash_sha1.as_ref().unwrap().verify(&content));
+		assert!(package.download_hash_sha256.as_ref().unwrap().verify(&content));
+		assert_eq!(package.install_size, Some(14));
+		assert_eq!(package.install.len(), 1);
+		/* `find` was added in v1.4 */
+		assert_eq!(package.spec_version, SpecVersion::from_parts(1, 4));
+
+		let archive = dir.path().join("Packed.zip");
+		write_zip(&archive, &["Packed/part.cfg"]);
+		let package = Package::generate_from_archive(&PackageTemplate { identifier: "Other".to_string(), ..template.clone() }, &archive).unwrap();
+		assert_eq!(package.spec_version, SpecVersion::from_parts(1, 0));
+		assert_eq!(package.write_to_json()["spec_version"], serde_json::json!(1));
+		let package = Package::generate_from_archive(&PackageTemplate { identifier: "Other".to_string(), license: vec!["MIT".to_string(), "CC-BY-4.0".to_string()], ..template.clone() }, &archive).unwrap();
+		assert_eq!(package.spec_version, SpecVersion::from_parts(1, 8));
+
+		let template = PackageTemplate { identifier: "Not A Valid Identifier!".to_string(), ..template };
+		assert!(Package::generate_from_archive(&template, &archive).is_err());
+	}
+}
@@ -0,0 +1,126 @@
+use serde::*;
+use sha1::Digest;
+
+/// The algorithms used for the `download_hash` of a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+	Sha1,
+	Sha256,
+}
+
+impl HashAlgorithm {
+	/// Length of a digest in bytes.
+	pub fn digest_len(&self) -> usize {
+		match self {
+			Self::Sha1 => 20,
+			Self::Sha256 => 32,
+		}
+	}
+}
+
+impl std::fmt::Display for HashAlgorithm {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Sha1 => write!(f, "SHA-1"),
+			Self::Sha256 => write!(f, "SHA-256"),
+		}
+	}
+}
+
+/// A digest of a package's download.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DownloadHash {
+	algorithm: HashAlgorithm,
+	digest: Vec<u8>,
+}
+
+impl DownloadHash {
+	/// Reads a hex encoded digest, upper and lower case are accepted.
+	pub fn from_hex(algorithm: HashAlgorithm, hex: &str) -> crate::Result<Self> {
+		let digest = hex::decode(hex).map_err(|_| crate::Error::Parse(format!("{} hash {} is not valid hex", algorithm, hex)))?;
+		if digest.len() != algorithm.digest_len() {
+			return Err(crate::Error::Parse(format!("{} hash {} has the wrong length", algorithm, hex)))
+		}
+		Ok(Self { algorithm, digest })
+	}
+
+	/// Hashes `data` using `algorithm`.
+	pub fn compute(algorithm: HashAlgorithm, data: &[u8]) -> Self {
+		let mut hasher = DownloadHasher::new(algorithm);
+		hasher.update(data);
+		hasher.finish()
+	}
+
+	pub fn algorithm(&self) -> HashAlgorithm {
+		self.algorithm
+	}
+
+	pub fn digest(&self) -> &[u8] {
+		&self.digest
+	}
+
+	/// The digest as lowercase hex.
+	pub fn to_hex(&self) -> String {
+		hex::encode(&self.digest)
+	}
+
+	/// Checks if `data` has this digest.
+	pub fn verify(&self, data: &[u8]) -> bool {
+		Self::compute(self.algorithm, data) == *self
+	}
+}
+
+impl std::fmt::Display for DownloadHash {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} {}", self.algorithm, self.to_hex())
+	}
+}
+
+/// Incrementally computes a [`DownloadHash`] for data which isn't all in memory at once.
+pub enum DownloadHasher {
+	Sha1(sha1::Sha1),
+	Sha256(sha2::Sha256),
+}
+
+impl DownloadHasher {
+	pub fn new(algorithm: HashAlgorithm) -> Self {
+		match algorithm {
+			HashAlgorithm::Sha1 => Self::Sha1(sha1::Sha1::new()),
+			HashAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+		}
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		match self {
+			Self::Sha1(h) => h.update(data),
+			Self::Sha256(h) => h.update(data),
+		}
+	}
+
+	pub fn finish(self) -> DownloadHash {
+		match self {
+			Self::Sha1(h) => DownloadHash { algorithm: HashAlgorithm::Sha1, digest: h.finalize().to_vec() },
+			Self::Sha256(h) => DownloadHash { algorithm: HashAlgorithm::Sha256, digest: h.finalize().to_vec() },
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn hashes_decode_and_verify() {
+		let sha1 = DownloadHash::from_hex(HashAlgorithm::Sha1, "A9993E364706816ABA3E25717850C26C9CD0D89D").unwrap();
+		assert_eq!(sha1, DownloadHash::from_hex(HashAlgorithm::Sha1, "a9993e364706816aba3e25717850c26c9cd0d89d").unwrap());
+		assert!(sha1.verify(b"abc"));
+		assert!(!sha1.verify(b"abd"));
+
+		let sha256 = DownloadHash::from_hex(HashAlgorithm::Sha256, "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD").unwrap();
+		assert!(sha256.verify(b"abc"));
+		assert_eq!(sha256.to_hex(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+		assert!(DownloadHash::from_hex(HashAlgorithm::Sha256, "A9993E364706816ABA3E25717850C26C9CD0D89D").is_err());
+		assert!(DownloadHash::from_hex(HashAlgorithm::Sha1, "not hex").is_err());
+	}
+}
@@ -0,0 +1,284 @@
+//! Functions and methods for writing CKAN types as spec conformant JSON
+
+use serde_json::{json, Map, Value};
+use super::*;
+
+/// Writes a "one or many" style value, a single element is written as a plain string.
+fn one_or_many(values: &[String]) -> Value {
+	match values {
+		[value] => json!(value),
+		values => json!(values),
+	}
+}
+
+/// Writes a bound using the given keys for the explicit, minimum and maximum versions, [`Any`](VersionBounds::Any) writes nothing.
+fn write_bounds<T>(obj: &mut Map<String, Value>, bounds: &VersionBounds<T>, keys: [&str; 3], to_string: impl Fn(&T) -> String)
+where T: std::cmp::PartialEq + std::cmp::Ord + std::clone::Clone {
+	let [explicit, min_key, max_key] = keys;
+	let mut put = |key: &str, v: &T| { obj.insert(key.to_string(), json!(to_string(v))); };
+	match bounds {
+		VersionBounds::Any => {},
+		VersionBounds::Explicit(v) => put(explicit, v),
+		VersionBounds::MinOnly(min) => put(min_key, min),
+		VersionBounds::MaxOnly(max) => put(max_key, max),
+		VersionBounds::MinMax(min, max) => {
+			put(min_key, min);
+			put(max_key, max);
+		},
+	}
+}
+
+impl install::InstallDirective {
+	pub fn to_json(&self) -> Value {
+		use install::*;
+
+		let mut obj = Map::new();
+		match &self.source {
+			SourceDirective::File(f) => obj.insert("file".to_string(), json!(f)),
+			SourceDirective::Find(f) => obj.insert("find".to_string(), json!(f)),
+			SourceDirective::FindRegExp(f) => obj.insert("find_regexp".to_string(), json!(f)),
+		};
+		obj.insert("install_to".to_string(), json!(self.install_to));
+		for directive in &self.additional {
+			let (key, value) = match directive {
+				OptionalDirective::As(v) => ("as", json!(v)),
+				OptionalDirective::Filter(v) => ("filter", one_or_many(v)),
+				OptionalDirective::FilterRegExp(v) => ("filter_regexp", one_or_many(v)),
+				OptionalDirective::IncludeOnly(v) => ("include_only", one_or_many(v)),
+				OptionalDirective::IncludeOnlyRegExp(v) => ("include_only_regexp", one_or_many(v)),
+				OptionalDirective::FindMatchesFiles(v) => ("find_matches_files", json!(v)),
+			};
+			obj.insert(key.to_string(), value);
+		}
+		Value::Object(obj)
+	}
+}
+
+impl relationship::PackageDescriptor {
+	pub fn to_json(&self) -> Value {
+		let mut obj = Map::new();
+		obj.insert("name".to_string(), json!(self.name));
+		write_bounds(&mut obj, &self.version, ["version", "min_version", "max_version"], |v| v.to_spec_string());
+		Value::Object(obj)
+	}
+}
+
+impl relationship::Relationship {
+	pub fn to_json(relationships: &[relationship::Relationship]) -> Value {
+		use relationship::*;
+
+		relationships.iter().map(|relationship| {
+			let mut obj = match relationship {
+				Relationship::AnyOf { any_of, choice_help_text, .. } => {
+					let mut obj = Map::new();
+					obj.insert("any_of".to_string(), any_of.iter().map(|d| d.to_json()).collect());
+					if let Some(text) = choice_help_text {
+						obj.insert("choice_help_text".to_string(), json!(text));
+					}
+					obj
+				},
+				Relationship::One { descriptor, .. } => match descriptor.to_json() {
+					Value::Object(obj) => obj,
+					_ => unreachable!("descriptors are written as objects."),
+				},
+			};
+			if relationship.suppress_recommendations() {
+				obj.insert("suppress_recommendations".to_string(), json!(true));
+			}
+			Value::Object(obj)
+		}).collect()
+	}
+}
+
+impl resources::Resources {
+	pub fn to_json(&self) -> Value {
+		let mut obj = self.iter()
+			.map(|(k, v)| (k.to_string(), json!(v)))
+			.collect::<Map<_, _>>();
+		obj.extend(self.extensions.iter().map(|(k, v)| (k.clone(), v)));
+		Value::Object(obj)
+	}
+}
+
+impl spec_version::SpecVersion {
+	/// Writes `v1.0` as the integer `1` and anything else as a `vMAJOR.MINOR` string.
+	pub fn to_json(&self) -> Value {
+		if *self == Self::from_parts(1, 0) {
+			json!(1)
+		} else {
+			json!(self.to_string())
+		}
+	}
+}
+
+impl Package {
+	/// Writes the package as a `.ckan` file.
+	///
+	/// Fields with default values are left out, reading the result with [`read_from_json()`](Package::read_from_json()) gives the same package.
+	pub fn write_to_json(&self) -> Value {
+		let mut obj = Map::new();
+		let mut put = |key: &str, value: Value| { obj.insert(key.to_string(), value); };
+
+		put("spec_version", self.spec_version.to_json());
+		put("identifier", json!(self.identifier.identifier));
+		put("version", json!(self.identifier.version.to_spec_string()));
+		put("name", json!(self.name));
+		put("abstract", json!(self.blurb));
+		put("author", one_or_many(&self.author));
+		if let Some(download) = &self.download {
+			put("download", json!(download));
+		}
+		put("license", one_or_many(&self.license));
+
+		if !self.install.is_empty() {
+			put("install", self.install.iter().map(|i| i.to_json()).collect());
+		}
+		if let Some(description) = &self.description {
+			put("description", json!(description));
+		}
+		if let Some(comment) = &self.comment {
+			put("comment", json!(comment));
+		}
+		match self.release_status {
+			ReleaseStatus::Stable => {},
+			ReleaseStatus::Testing => put("release_status", json!("testing")),
+			ReleaseStatus::Development => put("release_status", json!("development")),
+		}
+		if self.ksp_version_strict != (self.spec_version < SpecVersion::KSP_VERSION_STRICT_OPTIONAL) {
+			put("ksp_version_strict", json!(self.ksp_version_strict));
+		}
+		if let Some(tags) = &self.tags {
+			put("tags", json!(tags));
+		}
+		if let Some(localizations) = &self.localizations {
+			put("localizations", json!(localizations));
+		}
+		if let Some(size) = self.download_size {
+			put("download_size", json!(size));
+		}
+		let hashes = [&self.download_hash_sha1, &self.download_hash_sha256].into_iter()
+			.flatten()
+			.map(|h| {
+				let key = match h.algorithm() {
+					HashAlgorithm::Sha1 => "sha1",
+					HashAlgorithm::Sha256 => "sha256",
+				};
+				(key.to_string(), json!(h.to_hex().to_uppercase()))
+			})
+			.collect::<Map<_, _>>();
+		if !hashes.is_empty() {
+			put("download_hash", Value::Object(hashes));
+		}
+		if let Some(content_type) = &self.download_content_type {
+			put("download_content_type", json!(content_type));
+		}
+		if let Some(size) = self.install_size {
+			put("install_size", json!(size));
+		}
+		if let Some(date) = &self.release_date {
+			put("release_date", json!(date.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)));
+		}
+		for kind in RelationshipKind::ALL {
+			let relationships = self.get_relationships(kind);
+			if !relationships.is_empty() {
+				put(&kind.to_string(), relationship::Relationship::to_json(relationships));
+			}
+		}
+		if let Some(replaced_by) = &self.replaced_by {
+			put("replaced_by", replaced_by.to_json());
+		}
+		match self.kind {
+			Kind::Package => {},
+			Kind::MetaPackage => put("kind", json!("metapackage")),
+			Kind::DLC => put("kind", json!("dlc")),
+		}
+		if !self.provides.is_empty() {
+			let mut provides = self.provides.iter().collect::<Vec<_>>();
+			provides.sort();
+			put("provides", json!(provides));
+		}
+		let resources = self.resources.to_json();
+		if resources.as_object().is_some_and(|r| !r.is_empty()) {
+			put("resources", resources);
+		}
+
+		write_bounds(&mut obj, &self.ksp_version, ["ksp_version", "ksp_version_min", "ksp_version_max"], |v| v.to_string());
+		obj.extend(self.extensions.iter().map(|(k, v)| (k.clone(), v)));
+		Value::Object(obj)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn read_write_round_trip() {
+		let json = json!({
+			"spec_version": "v1.26",
+			"identifier": "Example",
+			"version": "1:1.0.2",
+			"name": "Example",
+			"abstract": "An example package.",
+			"author": ["tester", "other"],
+			"download": "https://example.com/package.zip",
+			"license": "MIT",
+			"install": [
+				{ "find": "Example", "install_to": "GameData", "filter": ["Thumbs.db", "Source"], "find_matches_files": true },
+				{ "file": "Extras/Example.cfg", "install_to": "GameData/Example", "as": "Settings.cfg" },
+			],
+			"description": "A longer description.",
+			"comment": "Written by hand.",
+			"release_status": "testing",
+			"ksp_version_min": "1.8",
+			"ksp_version_max": "1.12.5",
+			"ksp_version_strict": true,
+			"tags": ["parts"],
+			"localizations": ["en-us", "de-de"],
+			"download_size": 1024,
+			"download_hash": {
+				"sha1": "A9993E364706816ABA3E25717850C26C9CD0D89D",
+				"sha256": "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD",
+			},
+			"download_content_type": "application/zip",
+			"install_size": 4096,
+			"release_date": "2021-06-01T10:30:00Z",
+			"depends": [
+				{ "name": "ModuleManager", "min_version": "4.0" },
+				{ "any_of": [{ "name": "A", "version": "1.0" }, { "name": "B" }], "choice_help_text": "Pick one.", "suppress_recommendations": true },
+			],
+			"conflicts": [{ "name": "Old", "max_version": "0.9" }],
+			"replaced_by": { "name": "Example2" },
+			"kind": "metapackage",
+			"provides": ["ExampleCore", "ExampleParts"],
+			"resources": {
+				"homepage": "https://example.com",
+				"remote-avc": "https://example.com/Example.version",
+				"x_screenshot": "https://example.com/screenshot.png",
+				"x_wiki": "https://example.com/wiki",
+			},
+			"x_generated_by": { "tool": "test" },
+		});
+
+		let package = Package::read_from_json(json.clone()).unwrap();
+		let written = package.write_to_json();
+		assert_eq!(written, json);
+		assert_eq!(Package::read_from_json(written.clone()).unwrap().write_to_json(), written);
+	}
+
+	#[test]
+	fn defaults_are_left_out() {
+		let json = json!({
+			"spec_version": 1,
+			"identifier": "Minimal",
+			"version": "1.0",
+			"name": "Minimal",
+			"abstract": "A minimal package.",
+			"author": "tester",
+			"download": "https://example.com/package.zip",
+			"license": "MIT",
+			"ksp_version": "1.12",
+		});
+		assert_eq!(Package::read_from_json(json.clone()).unwrap().write_to_json(), json);
+	}
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+use serde::*;
+
+/// Fields prefixed with `x_` which aren't part of the spec.
+///
+/// The values can be any JSON, they're kept as JSON text so the DB can be saved in formats
+/// which can't represent arbitrary JSON.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Extensions(BTreeMap<String, String>);
+
+impl Extensions {
+	/// Gets the value of a field, `key` includes the `x_` prefix.
+	pub fn get(&self, key: &str) -> Option<serde_json::Value> {
+		self.0.get(key).and_then(|v| serde_json::from_str(v).ok())
+	}
+
+	/// Gets the value of a field as JSON text.
+	pub fn get_raw(&self, key: &str) -> Option<&str> {
+		self.0.get(key).map(|v| v.as_str())
+	}
+
+	/// Sets a field, returning the previous value.
+	pub fn insert(&mut self, key: String, value: &serde_json::Value) -> Option<serde_json::Value> {
+		self.0.insert(key, value.to_string()).and_then(|v| serde_json::from_str(&v).ok())
+	}
+
+	/// Iterates over the fields sorted by key.
+	pub fn iter(&self) -> impl Iterator<Item = (&String, serde_json::Value)> {
+		self.0.iter().filter_map(|(k, v)| serde_json::from_str(v).ok().map(|v| (k, v)))
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
@@ -97,6 +97,21 @@ impl package_version::PackageVersion {
 	}
 }
 
+impl spec_version::SpecVersion {
+	/// Reads either the integer `1` or a `vMAJOR.MINOR` string.
+	pub fn from_json(v: &serde_json::Value) -> crate::Result<Self> {
+		use crate::Error::Parse;
+		match v {
+			serde_json::Value::Number(n) => n.as_u64()
+				.and_then(|n| u32::try_from(n).ok())
+				.map(|n| Self::from_parts(n, 0))
+				.ok_or_else(|| Parse(format!("spec version {} must be a positive integer", n))),
+			serde_json::Value::String(s) => Self::new(s),
+			_ => Err(Parse("spec_version must be a number or string".to_string())),
+		}
+	}
+}
+
 impl relationship::PackageDescriptor {
 	pub fn from_json(v: &serde_json::Value) -> crate::Result<Self> {
 		use crate::Error::Parse;
@@ -125,6 +140,10 @@ impl relationship::Relationship {
 		let v = v.as_array().ok_or(Parse("must be array".to_string()))?;
 		for element in v {
 			let obj = element.as_object().ok_or(Parse("array elements must be objects".to_string()))?;
+			let suppress_recommendations = match obj.get("suppress_recommendations") {
+				Some(v) => v.as_bool().ok_or_else(|| Parse("suppress_recommendations must be a boolean".to_string()))?,
+				None => false,
+			};
 			let relationship = 
 				if let Some(f) = obj.get("any_of") {
 					let arr = f.as_array().ok_or(Parse("any_of constraint must be an array".to_string()))?;
@@ -133,9 +152,15 @@ impl relationship::Relationship {
 						if !o.is_object() { return Err(Parse("any_of array must contain only objects".to_string())); }
 						ships.push(PackageDescriptor::from_json(o)?);
 					}
-					Relationship::AnyOf(ships)
+					Relationship::AnyOf {
+						any_of: ships,
+						choice_help_text: obj.get("choice_help_text")
+							.map(|v| v.as_str().map(|s| s.to_string()).ok_or_else(|| Parse("choice_help_text must be a string".to_string())))
+							.transpose()?,
+						suppress_recommendations,
+					}
 				} else if obj.get("name").is_some() {
-					Relationship::One(PackageDescriptor::from_json(element)?)
+					Relationship::One { descriptor: PackageDescriptor::from_json(element)?, suppress_recommendations }
 				} else {
 					return Err(Parse("relationship object must be a relationship or any_of constraint".to_string()));
 				};
@@ -146,6 +171,86 @@ impl relationship::Relationship {
 	}
 }
 
+impl resources::Resources {
+	pub fn from_json(v: &serde_json::Value) -> crate::Result<Self> {
+		use crate::Error::Parse;
+
+		let obj = v.as_object().ok_or_else(|| Parse("resources must be an object".to_string()))?;
+		let get = |key: &str| -> crate::Result<Option<String>> {
+			obj.get(key)
+				.map(|v| v.as_str().map(|s| s.to_string()).ok_or_else(|| Parse(format!("resource {} must be a string", key))))
+				.transpose()
+		};
+
+		Ok(Self {
+			homepage: get("homepage")?,
+			bugtracker: get("bugtracker")?,
+			license: get("license")?,
+			discussions: get("discussions")?,
+			repository: get("repository")?,
+			ci: get("ci")?,
+			spacedock: get("spacedock")?,
+			curse: get("curse")?,
+			manual: get("manual")?,
+			metanetkan: get("metanetkan")?,
+			remote_avc: get("remote-avc")?,
+			remote_swinfo: get("remote-swinfo")?,
+			store: get("store")?,
+			steamstore: get("steamstore")?,
+			gogstore: get("gogstore")?,
+			epicstore: get("epicstore")?,
+			x_screenshot: get("x_screenshot")?,
+			extensions: extensions::Extensions::from_json(v, &["x_screenshot"]),
+		})
+	}
+}
+
+impl extensions::Extensions {
+	/// Collects the fields of an object prefixed with `x_`, except those in `known`.
+	pub(super) fn from_json(v: &serde_json::Value, known: &[&str]) -> Self {
+		let mut extensions = Self::default();
+		for (key, value) in v.as_object().into_iter().flatten() {
+			if key.starts_with("x_") && !known.contains(&key.as_str()) {
+				extensions.insert(key.clone(), value);
+			}
+		}
+		extensions
+	}
+}
+
+/// Reads one of the digests from the `download_hash` object.
+fn get_download_hash(obj: &serde_json::Value, algorithm: HashAlgorithm) -> crate::Result<Option<DownloadHash>> {
+	let key = match algorithm {
+		HashAlgorithm::Sha1 => "sha1",
+		HashAlgorithm::Sha256 => "sha256",
+	};
+	obj.get("download_hash")
+		.and_then(|h| h.get(key))
+		.map(|hash| {
+			hash.as_str()
+				.ok_or_else(|| crate::Error::Parse(format!("download_hash {} must be a string", key)))
+				.and_then(|hash| DownloadHash::from_hex(algorithm, hash))
+		})
+		.transpose()
+}
+
+/// Reads a `release_date`, either an ISO 8601 date or date and time.
+///
+/// Dates without a time are midnight and times without an offset are UTC.
+fn parse_release_date(date: &str) -> crate::Result<chrono::DateTime<chrono::Utc>> {
+	use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+	if let Ok(d) = DateTime::parse_from_rfc3339(date) {
+		return Ok(d.with_timezone(&Utc))
+	}
+	if let Ok(d) = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f") {
+		return Ok(d.and_utc())
+	}
+	NaiveDate::parse_from_str(date, "%Y-%m-%d")
+		.map(|d| d.and_hms_opt(0, 0, 0).expect("midnight should be a valid time.").and_utc())
+		.map_err(|_| crate::Error::Parse(format!("release_date {} is not an ISO 8601 date", date)))
+}
+
 impl Package {
 	pub fn read_from_json(v: serde_json::Value) -> crate::Result<Self> {
 		use crate::Error::Parse;
@@ -174,14 +279,13 @@ impl Package {
 		/* FIXME: Lots of panics and error ignorance */
 
 		let obj = &v;
+		let spec_version = spec_version::SpecVersion::from_json(obj.get("spec_version").ok_or_else(|| Parse("`spec_version` is missing".to_string()))?)?;
+		if !spec_version.is_supported() {
+			return Err(Parse(format!("spec version {} is newer than the supported version {}", spec_version, spec_version::SpecVersion::SUPPORTED)))
+		}
+
 		Ok( Package {
-			spec_version: {
-				match obj.get("spec_version").ok_or_else(|| Parse("`spec_version` is missing".to_string()))? {
-					Value::Number(v) => v.to_string(),
-					Value::String(v) => v.to_owned(),
-					_ => return Err(Parse("invalid type".to_string())),
-				}
-			},
+			spec_version,
 			identifier: relationship::PackageIdentifier {
 				identifier: get_val(obj, "identifier")?,
 				version: obj.get("version")
@@ -211,6 +315,7 @@ impl Package {
 				}
 			},
 			description: get_val_optional(obj, "description")?,
+			comment: get_val_optional(obj, "comment")?,
 			release_status: {
 				match obj.get("release_status") {
 					Some(v) => {
@@ -246,54 +351,33 @@ impl Package {
 					"This field defaults to false, including for spec_versions less than v1.16,
 					however CKAN clients prior to v1.16 would only perform strict checking."
 					
-					So I would say the default needs to be false for spec versions >= v1.16
-					and otherwise true.
+					So the default is false for spec versions >= v1.16 and otherwise true.
 				 */
 				Some(v) => {
 					v.as_bool().ok_or(Parse("ksp_version_strict must be a boolean".into()))?
 				},
-				None => false /* TODO: true for earlier spec versions */,
+				None => spec_version < spec_version::SpecVersion::KSP_VERSION_STRICT_OPTIONAL,
 			},
 			tags: get_one_or_many_string(obj, "tags").ok(), /* This does work */
 			localizations: get_one_or_many_string(obj, "localizations").ok(),
 			download_size: get_val_optional(obj, "download_size")?,
-			download_hash_sha1: {
-				/* Looks bad but the functional equivalent looks worse */
-				let mut res = None;
-				if let Some(h) = obj.get("download_hash") {
-					if let Some(o) = h.as_object() {
-						if let Some(hash) = o.get("sha1") {
-							if let Some(s) = hash.as_str() {
-								res = s.as_bytes().try_into().ok();
-							}
-						}
-					}
-				}
-				res
-			},
-			download_hash_sha256: {
-				let mut res = None;
-				if let Some(h) = obj.get("download_hash") {
-					if let Some(o) = h.as_object() {
-						if let Some(hash) = o.get("sha256") {
-							if let Some(s) = hash.as_str() {
-								res = s.as_bytes().try_into().ok();
-							}
-						}
-					}
-				}
-				res
-			},
+			download_hash_sha1: get_download_hash(obj, HashAlgorithm::Sha1)?,
+			download_hash_sha256: get_download_hash(obj, HashAlgorithm::Sha256)?,
 			download_content_type: get_val_optional(obj, "download_content_type")?,
 			install_size: get_val_optional(obj, "install_size")?,
-			release_date: get_val_optional(obj, "release_date")?,
+			release_date: get_val_optional::<String>(obj, "release_date")?.try_map(|d| parse_release_date(&d))?,
 			depends: match obj.get("depends") { Some(v) => relationship::Relationship::from_json(v)?, None => Default::default()},
 			recommends: match obj.get("recommends") { Some(v) => relationship::Relationship::from_json(v)?, None => Default::default()},
 			suggests: match obj.get("suggests") { Some(v) => relationship::Relationship::from_json(v)?, None => Default::default()},
 			supports: match obj.get("supports") { Some(v) => relationship::Relationship::from_json(v)?, None => Default::default()},
 			conflicts: match obj.get("conflicts") { Some(v) => relationship::Relationship::from_json(v)?, None => Default::default()},
-			replaced_by: obj.get("replaced-by").try_map(relationship::PackageDescriptor::from_json)?,
-			kind: get_val(obj, "kind").unwrap_or_default(),
+			replaced_by: obj.get("replaced_by").try_map(relationship::PackageDescriptor::from_json)?,
+			kind: match get_val_optional::<String>(obj, "kind")?.as_deref() {
+				None | Some("package") => Kind::Package,
+				Some("metapackage") => Kind::MetaPackage,
+				Some("dlc") => Kind::DLC,
+				Some(k) => return Err(Parse(format!("unknown kind {}", k))),
+			},
 			provides: {
 				obj.get("provides").and_then(|value|
 					value.as_array()
@@ -302,8 +386,79 @@ impl Package {
 					.collect::<HashSet<_>>())
 				).unwrap_or_default()
 			},
-			resources: get_val(obj, "resources").unwrap_or_default(), /* FIXME: doesn't handle read errors */
+			resources: obj.get("resources").try_map(resources::Resources::from_json)?.unwrap_or_default(),
+			extensions: extensions::Extensions::from_json(obj, &[]),
 		})
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn read_optional_fields() {
+		let package = Package::read_from_json(serde_json::json!({
+			"spec_version": "v1.26",
+			"identifier": "Example",
+			"version": "1.0",
+			"name": "Example",
+			"abstract": "An example package.",
+			"author": "tester",
+			"license": "MIT",
+			"download": "https://example.com/package.zip",
+			"comment": "Generated by hand.",
+			"release_date": "2021-06-01T12:30:00+02:00",
+			"replaced_by": { "name": "Example2", "min_version": "2.0" },
+			"resources": {
+				"homepage": "https://example.com",
+				"remote-avc": "https://example.com/Example.version",
+				"x_screenshot": "https://example.com/screenshot.png",
+				"x_wiki": "https://example.com/wiki",
+			},
+			"recommends": [
+				{ "name": "Other", "suppress_recommendations": true },
+				{ "any_of": [{ "name": "A" }, { "name": "B" }], "choice_help_text": "Pick one." },
+			],
+			"x_netkan_override": { "enabled": true },
+		})).unwrap();
+
+		assert_eq!(package.comment.as_deref(), Some("Generated by hand."));
+		assert_eq!(package.release_date, Some(parse_release_date("2021-06-01T10:30:00Z").unwrap()));
+		assert_eq!(package.replaced_by.unwrap().name, "Example2");
+		assert_eq!(package.resources.homepage.as_deref(), Some("https://example.com"));
+		assert_eq!(package.resources.remote_avc.as_deref(), Some("https://example.com/Example.version"));
+		assert!(package.resources.x_screenshot.is_some());
+		assert_eq!(package.resources.extensions.get("x_wiki"), Some(serde_json::json!("https://example.com/wiki")));
+		assert_eq!(package.resources.iter().count(), 3);
+		assert!(package.recommends[0].suppress_recommendations());
+		assert_eq!(package.recommends[1].choice_help_text(), Some("Pick one."));
+		assert_eq!(package.extensions.get("x_netkan_override"), Some(serde_json::json!({ "enabled": true })));
+
+		assert_eq!(parse_release_date("2021-06-01").unwrap(), parse_release_date("2021-06-01T00:00:00").unwrap());
+		assert!(parse_release_date("June 1st").is_err());
+	}
+
+	#[test]
+	fn spec_version_defaults_and_limits() {
+		let read = |spec_version: serde_json::Value| Package::read_from_json(serde_json::json!({
+			"spec_version": spec_version,
+			"identifier": "Example",
+			"version": "1.0",
+			"name": "Example",
+			"abstract": "An example package.",
+			"author": "tester",
+			"license": "MIT",
+			"download": "https://example.com/package.zip",
+		}));
+
+		let package = read(serde_json::json!(1)).unwrap();
+		assert_eq!(package.spec_version, SpecVersion::from_parts(1, 0));
+		assert!(package.ksp_version_strict);
+		assert!(read(serde_json::json!("v1.4")).unwrap().ksp_version_strict);
+		assert!(!read(serde_json::json!("v1.16")).unwrap().ksp_version_strict);
+		assert!(read(serde_json::json!("v99.0")).is_err());
+		assert!(read(serde_json::json!("latest")).is_err());
+	}
+}
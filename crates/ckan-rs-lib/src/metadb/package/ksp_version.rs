@@ -94,6 +94,7 @@ impl KspVersionReal {
 	/// # How This Is Defined
 	/// 1. `major` and `minor` must match.
 	/// 1. If `patch` is present in both, `self <= other`.
+	///
 	/// any other combination of patch options is considered compatible.
 	pub fn is_compatible_with(&self, other: &Self) -> bool {
 		if self.major() == other.major() && self.minor() == other.minor() {
@@ -117,6 +118,19 @@ impl KspVersionReal {
 	pub fn set_build(&mut self, build: Option<u32>) { self.build = build; }
 }
 
+impl std::fmt::Display for KspVersionReal {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}.{}", self.major, self.minor)?;
+		if let Some(patch) = self.patch {
+			write!(f, ".{}", patch)?;
+			if let Some(build) = self.build {
+				write!(f, ".{}", build)?;
+			}
+		}
+		Ok(())
+	}
+}
+
 impl TryFrom<&str> for KspVersionReal {
 	type Error = crate::Error;
 	
@@ -193,12 +207,13 @@ impl KspVersionBounds {
 	/// 
 	/// # Parameters
 	/// - `strict` - Should this check require the version to be exactly equal or just compatible.
+	///   Components missing from the bound match any value, so a strict `1.12` accepts `1.12.5`.
 	pub fn is_version_compatible(&self, other: &KspVersionReal, strict: bool) -> bool {
 		match self {
 			Self::Any => true,
 			Self::Explicit(v) => {
 				if strict {
-					other == v
+					v.major() == other.major() && v.minor() == other.minor() && v.patch().is_none_or(|p| other.patch() == Some(p))
 				} else {
 					v.is_compatible_with(other)
 				}
@@ -221,5 +236,12 @@ mod test {
 	#[test] fn ksp_version_higher_version_is_gt() { assert!(KspVersionReal::new("1.12.1").unwrap() < KspVersionReal::new("1.12.2").unwrap()) }
 	#[test] fn ksp_version_le() { assert!(KspVersionReal::new("1.10").unwrap() <= KspVersionReal::new("1.11").unwrap() && KspVersionReal::new("1.10").unwrap() <= KspVersionReal::new("1.10").unwrap()) }
 	#[test] fn ksp_version_ge() { assert!(KspVersionReal::new("1.11").unwrap() <= KspVersionReal::new("1.12").unwrap() && KspVersionReal::new("1.11").unwrap() <= KspVersionReal::new("1.11").unwrap()) }
+	#[test] fn ksp_version_strict_accepts_unspecified_patch() {
+		let bounds = KspVersionBounds::new_from_str(Some("1.12"), None::<&str>, None::<&str>).unwrap();
+		assert!(bounds.is_version_compatible(&KspVersionReal::new("1.12.5").unwrap(), true));
+		let bounds = KspVersionBounds::new_from_str(Some("1.12.3"), None::<&str>, None::<&str>).unwrap();
+		assert!(!bounds.is_version_compatible(&KspVersionReal::new("1.12.5").unwrap(), true));
+		assert!(bounds.is_version_compatible(&KspVersionReal::new("1.12.5").unwrap(), false));
+	}
 	#[test] fn ksp_version_build_has_no_effect() { assert!(KspVersionReal::new("1.12.1").unwrap() == KspVersionReal::new("1.12.1.1234").unwrap()) }
 }
\ No newline at end of file
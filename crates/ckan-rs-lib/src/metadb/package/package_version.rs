@@ -5,7 +5,7 @@ use serde::*;
 /// # Format
 /// Package versions follow a format `[epoch:]mod_version`.
 /// - `epoch` is used to correct errors in versioning schemes or
-/// organize versions that are difficult to interpret.
+///   organize versions that are difficult to interpret.
 /// - `version` can technically be *any* string according to the spec.
 #[derive(Debug, Clone, Eq, Serialize, Deserialize)]
 pub struct PackageVersion {
@@ -29,6 +29,17 @@ impl PackageVersion {
 	}
 }
 
+impl PackageVersion {
+	/// Formats the version as it appears in `.ckan` files, the epoch is left out when it is `0`.
+	pub fn to_spec_string(&self) -> String {
+		if self.epoch == 0 {
+			self.version.clone()
+		} else {
+			self.to_string()
+		}
+	}
+}
+
 impl TryFrom<String> for PackageVersion {
 	type Error = crate::Error;
 	fn try_from(value: String) -> Result<Self, Self::Error> { Self::new(&value) }
@@ -172,7 +183,7 @@ impl std::hash::Hash for PackageVersion {
 }
 
 impl std::fmt::Display for PackageVersion {
-	fn fmt(&self, f: &mut __private::Formatter<'_>) -> std::fmt::Result {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		write!(f, "{}:{}", self.epoch, self.version)
 	}
 }
@@ -32,7 +32,7 @@ impl std::cmp::PartialOrd for PackageIdentifier {
 }
 
 impl std::fmt::Display for PackageIdentifier {
-	fn fmt(&self, f: &mut __private::Formatter<'_>) -> std::fmt::Result {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		write!(f, "{}-{}", self.identifier, self.version)
 	}
 }
@@ -66,9 +66,19 @@ impl PackageDescriptor {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Relationship {
 	/// At least one of the descriptors must match to fulfill the relationship.
-	AnyOf(Vec<PackageDescriptor>),
+	AnyOf {
+		any_of: Vec<PackageDescriptor>,
+		/// Shown to the user when choosing which of the descriptors to install.
+		choice_help_text: Option<String>,
+		/// Don't install the recommendations of the chosen package.
+		suppress_recommendations: bool,
+	},
 	/// This single descriptor requirement must be met.
-	One(PackageDescriptor),
+	One {
+		descriptor: PackageDescriptor,
+		/// Don't install the recommendations of the package fulfilling the relationship.
+		suppress_recommendations: bool,
+	},
 }
 
 impl Relationship {
@@ -76,8 +86,48 @@ impl Relationship {
 	/// Convienience function to collapse this relationship into a vector
 	pub fn as_vec(&self) -> Vec<&PackageDescriptor> {
 		match self {
-			Relationship::AnyOf(v) => v.iter().collect::<Vec<_>>(),
-			Relationship::One(r) => vec![r],
+			Relationship::AnyOf { any_of, .. } => any_of.iter().collect::<Vec<_>>(),
+			Relationship::One { descriptor, .. } => vec![descriptor],
+		}
+	}
+
+	pub fn choice_help_text(&self) -> Option<&str> {
+		match self {
+			Relationship::AnyOf { choice_help_text, .. } => choice_help_text.as_deref(),
+			Relationship::One { .. } => None,
+		}
+	}
+
+	pub fn suppress_recommendations(&self) -> bool {
+		match self {
+			Relationship::AnyOf { suppress_recommendations, .. } | Relationship::One { suppress_recommendations, .. } => *suppress_recommendations,
+		}
+	}
+}
+
+/// The ways a package can relate to other packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RelationshipKind {
+	Depends,
+	Recommends,
+	Suggests,
+	Supports,
+	Conflicts,
+}
+
+impl RelationshipKind {
+	pub const ALL: [RelationshipKind; 5] = [Self::Depends, Self::Recommends, Self::Suggests, Self::Supports, Self::Conflicts];
+}
+
+/// Formats the kind as the key used in `.ckan` files.
+impl std::fmt::Display for RelationshipKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Depends => write!(f, "depends"),
+			Self::Recommends => write!(f, "recommends"),
+			Self::Suggests => write!(f, "suggests"),
+			Self::Supports => write!(f, "supports"),
+			Self::Conflicts => write!(f, "conflicts"),
 		}
 	}
 }
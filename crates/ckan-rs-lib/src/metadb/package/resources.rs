@@ -0,0 +1,59 @@
+use serde::*;
+use super::Extensions;
+
+/// Links to additional information about a package.
+///
+/// Every resource is a URL. Resources prefixed with `x_` which aren't known to the spec are kept in `extensions`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resources {
+	pub homepage: Option<String>,
+	pub bugtracker: Option<String>,
+	/// The license text, not to be confused with [`Package::license`](super::Package::license).
+	pub license: Option<String>,
+	pub discussions: Option<String>,
+	/// The source code repository.
+	pub repository: Option<String>,
+	/// Continuous integration builds.
+	pub ci: Option<String>,
+	pub spacedock: Option<String>,
+	pub curse: Option<String>,
+	pub manual: Option<String>,
+	/// The `.netkan` file the package was generated from.
+	pub metanetkan: Option<String>,
+	/// Alias for `remote-avc`, a KSP-AVC version file.
+	pub remote_avc: Option<String>,
+	/// Alias for `remote-swinfo`, a KSP2 `swinfo.json` file.
+	pub remote_swinfo: Option<String>,
+	pub store: Option<String>,
+	pub steamstore: Option<String>,
+	pub gogstore: Option<String>,
+	pub epicstore: Option<String>,
+	pub x_screenshot: Option<String>,
+	/// Any other `x_` resources.
+	pub extensions: Extensions,
+}
+
+impl Resources {
+	/// Iterates over the known resources which are set, using the names from the spec.
+	pub fn iter(&self) -> impl Iterator<Item = (&'static str, &String)> {
+		[
+			("homepage", &self.homepage),
+			("bugtracker", &self.bugtracker),
+			("license", &self.license),
+			("discussions", &self.discussions),
+			("repository", &self.repository),
+			("ci", &self.ci),
+			("spacedock", &self.spacedock),
+			("curse", &self.curse),
+			("manual", &self.manual),
+			("metanetkan", &self.metanetkan),
+			("remote-avc", &self.remote_avc),
+			("remote-swinfo", &self.remote_swinfo),
+			("store", &self.store),
+			("steamstore", &self.steamstore),
+			("gogstore", &self.gogstore),
+			("epicstore", &self.epicstore),
+			("x_screenshot", &self.x_screenshot),
+		].into_iter().filter_map(|(k, v)| v.as_ref().map(|v| (k, v)))
+	}
+}
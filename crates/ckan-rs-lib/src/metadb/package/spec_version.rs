@@ -0,0 +1,69 @@
+use serde::*;
+
+/// The version of the `.ckan` specification a package was written against.
+///
+/// # Format
+/// Either the integer `1`, meaning `v1.0`, or a string `vMAJOR.MINOR` such as `v1.26`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SpecVersion {
+	major: u32,
+	minor: u32,
+}
+
+impl SpecVersion {
+	/// The newest version of the spec this library can read.
+	pub const SUPPORTED: SpecVersion = SpecVersion { major: 1, minor: 34 };
+
+	/// Before this version `ksp_version_strict` was implied by clients.
+	pub const KSP_VERSION_STRICT_OPTIONAL: SpecVersion = SpecVersion { major: 1, minor: 16 };
+
+	pub const fn from_parts(major: u32, minor: u32) -> Self {
+		Self { major, minor }
+	}
+
+	/// Reads a version in the form `vMAJOR.MINOR`, the leading `v` is optional.
+	pub fn new(version: &str) -> crate::Result<Self> {
+		let err = || crate::Error::Parse(format!("spec version {} is not in the form vMAJOR.MINOR", version));
+		let (major, minor) = version.strip_prefix('v').unwrap_or(version)
+			.split_once('.')
+			.ok_or_else(err)?;
+		Ok(Self {
+			major: major.parse().map_err(|_| err())?,
+			minor: minor.parse().map_err(|_| err())?,
+		})
+	}
+
+	pub fn major(&self) -> u32 {
+		self.major
+	}
+
+	pub fn minor(&self) -> u32 {
+		self.minor
+	}
+
+	/// Returns `true` if packages of this version can be read by this library.
+	pub fn is_supported(&self) -> bool {
+		*self <= Self::SUPPORTED
+	}
+}
+
+impl std::fmt::Display for SpecVersion {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "v{}.{}", self.major, self.minor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn spec_version_parsing_and_order() {
+		assert_eq!(SpecVersion::new("v1.4").unwrap(), SpecVersion::from_parts(1, 4));
+		assert_eq!(SpecVersion::new("1.16").unwrap().to_string(), "v1.16");
+		assert!(SpecVersion::new("v1.4").unwrap() < SpecVersion::new("v1.16").unwrap());
+		assert!(SpecVersion::new("v1").is_err());
+		assert!(SpecVersion::new("vX.2").is_err());
+		assert!(!SpecVersion::from_parts(2, 0).is_supported());
+	}
+}
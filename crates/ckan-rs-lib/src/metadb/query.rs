@@ -0,0 +1,222 @@
+//! Composable queries over the packages in the MetaDB.
+//!
+//! # Example
+//! ```no_run
+//! use ckan_rs::metadb::{Filter, PackageQuery, SortBy};
+//! use ckan_rs::metadb::package::ReleaseStatus;
+//! # let db = ckan_rs::MetaDB::default();
+//! let query = PackageQuery::new()
+//!     .filter(Filter::Tag("graphics".into()).or(Filter::Tag("visual".into())))
+//!     .filter(Filter::ReleaseStatus(ReleaseStatus::Stable))
+//!     .filter(Filter::License("GPL-3.0".into()).not())
+//!     .sort_by(SortBy::ReleaseDate, true)
+//!     .latest_only();
+//! let packages = db.query(&query);
+//! ```
+
+use super::*;
+use package::{Kind, KspVersionReal, PackageVersionBounds, ReleaseStatus};
+use chrono::{DateTime, Utc};
+
+/// A condition a package must meet to be included in a [`PackageQuery`].
+///
+/// String comparisons are case insensitive.
+#[derive(Debug, Clone)]
+pub enum Filter {
+	Identifier(String),
+	/// Has the tag.
+	Tag(String),
+	/// Is authored by, or co-authored by, the author.
+	Author(String),
+	/// Is available under the license.
+	License(String),
+	ReleaseStatus(ReleaseStatus),
+	Kind(Kind),
+	/// Includes the localization.
+	Localization(String),
+	/// Released on or after a time. Packages without a release date don't match.
+	ReleasedAfter(DateTime<Utc>),
+	/// Released on or before a time. Packages without a release date don't match.
+	ReleasedBefore(DateTime<Utc>),
+	/// Compatible with any of the versions.
+	KspVersion(Vec<KspVersionReal>),
+	/// The package version is within the bounds.
+	Version(PackageVersionBounds),
+	/// Matches when all of the filters match, an empty list always matches.
+	All(Vec<Filter>),
+	/// Matches when any of the filters match, an empty list never matches.
+	Any(Vec<Filter>),
+	Not(Box<Filter>),
+}
+
+impl Filter {
+	/// Checks if `package` meets the condition.
+	pub fn matches(&self, package: &Package) -> bool {
+		let eq = |a: &String, b: &String| a.to_lowercase() == b.to_lowercase();
+		match self {
+			Filter::Identifier(identifier) => eq(&package.identifier.identifier, identifier),
+			Filter::Tag(tag) => package.tags.iter().flatten().any(|t| eq(t, tag)),
+			Filter::Author(author) => package.author.iter().any(|a| eq(a, author)),
+			Filter::License(license) => package.license.iter().any(|l| eq(l, license)),
+			Filter::ReleaseStatus(status) => &package.release_status == status,
+			Filter::Kind(kind) => &package.kind == kind,
+			Filter::Localization(localization) => package.localizations.iter().flatten().any(|l| eq(l, localization)),
+			Filter::ReleasedAfter(date) => package.release_date.is_some_and(|d| d >= *date),
+			Filter::ReleasedBefore(date) => package.release_date.is_some_and(|d| d <= *date),
+			Filter::KspVersion(versions) => versions.iter().any(|v| package.ksp_version.is_version_compatible(v, package.ksp_version_strict)),
+			Filter::Version(bounds) => bounds.is_version_within(&package.identifier.version),
+			Filter::All(filters) => filters.iter().all(|f| f.matches(package)),
+			Filter::Any(filters) => filters.iter().any(|f| f.matches(package)),
+			Filter::Not(filter) => !filter.matches(package),
+		}
+	}
+
+	/// Combines the filters so both must match.
+	pub fn and(self, other: Filter) -> Filter {
+		match self {
+			Filter::All(mut filters) => {
+				filters.push(other);
+				Filter::All(filters)
+			},
+			f => Filter::All(vec![f, other]),
+		}
+	}
+
+	/// Combines the filters so either must match.
+	pub fn or(self, other: Filter) -> Filter {
+		match self {
+			Filter::Any(mut filters) => {
+				filters.push(other);
+				Filter::Any(filters)
+			},
+			f => Filter::Any(vec![f, other]),
+		}
+	}
+
+	/// Inverts the filter.
+	#[allow(clippy::should_implement_trait)]
+	pub fn not(self) -> Filter {
+		Filter::Not(Box::new(self))
+	}
+}
+
+/// The order of the results of a [`PackageQuery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+	/// Sorted by name ignoring case.
+	Name,
+	/// Packages without a release date are always last.
+	ReleaseDate,
+	/// Sorted by package version then identifier.
+	Version,
+}
+
+/// A set of filters, an order and whether only the latest version of each identifier is kept.
+///
+/// Run with [`MetaDB::query()`].
+#[derive(Debug, Clone, Default)]
+pub struct PackageQuery {
+	filter: Option<Filter>,
+	sort: Option<(SortBy, bool)>,
+	latest_only: bool,
+}
+
+impl PackageQuery {
+	/// A query matching every package sorted by identifier and version.
+	pub fn new() -> Self {
+		Default::default()
+	}
+
+	/// Adds a filter, packages must match every filter added.
+	pub fn filter(mut self, filter: Filter) -> Self {
+		self.filter = Some(match self.filter {
+			Some(f) => f.and(filter),
+			None => filter,
+		});
+		self
+	}
+
+	/// Sets the order of the results.
+	pub fn sort_by(mut self, sort: SortBy, descending: bool) -> Self {
+		self.sort = Some((sort, descending));
+		self
+	}
+
+	/// Keeps only the newest version of each identifier from the packages matching the filters.
+	pub fn latest_only(mut self) -> Self {
+		self.latest_only = true;
+		self
+	}
+}
+
+impl MetaDB {
+	/// Gets the packages matching a [`PackageQuery`].
+	pub fn query(&self, query: &PackageQuery) -> Vec<&Package> {
+		let mut packages = self.packages.iter()
+			.filter(|p| query.filter.as_ref().is_none_or(|f| f.matches(p)))
+			.collect::<Vec<_>>();
+
+		if query.latest_only {
+			let mut latest = HashMap::<&str, &Package>::new();
+			for package in packages {
+				let entry = latest.entry(package.identifier.identifier.as_str()).or_insert(package);
+				if entry.identifier.version < package.identifier.version {
+					*entry = package;
+				}
+			}
+			packages = latest.into_values().collect();
+		}
+
+		/* Always sort by the identifier first so equal elements have a consistent order */
+		packages.sort();
+		if let Some((sort, descending)) = query.sort {
+			/* Only the sorted field is reversed so ties keep the identifier order */
+			let order = |ordering: std::cmp::Ordering| if descending { ordering.reverse() } else { ordering };
+			match sort {
+				SortBy::Name => packages.sort_by(|a, b| order(a.name.to_lowercase().cmp(&b.name.to_lowercase()))),
+				SortBy::Version => packages.sort_by(|a, b| order(a.identifier.version.cmp(&b.identifier.version))),
+				/* Undated packages are always last */
+				SortBy::ReleaseDate => packages.sort_by(|a, b| a.release_date.is_none().cmp(&b.release_date.is_none()).then_with(|| order(a.release_date.cmp(&b.release_date)))),
+			}
+		}
+		packages
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::package;
+	use serde_json::json;
+	use chrono::TimeZone;
+
+	#[test]
+	fn query_filters_sorts_and_collapses() {
+		let mut db = MetaDB::default();
+		db.put_package(package("Alpha", "1.0", json!({ "tags": ["parts"], "release_date": "2020-01-01" })));
+		db.put_package(package("Alpha", "2.0", json!({ "tags": ["parts"], "release_date": "2022-01-01" })));
+		db.put_package(package("Beta", "1.0", json!({ "tags": ["graphics"], "license": "GPL-3.0", "release_date": "2021-01-01" })));
+		db.put_package(package("Gamma", "1.0", json!({ "tags": ["Graphics"], "release_status": "testing" })));
+		db.put_package(package("Delta", "1.0", json!({ "tags": ["parts"], "release_date": "2021-01-01" })));
+
+		let ids = |packages: Vec<&Package>| packages.iter().map(|p| p.identifier.to_string()).collect::<Vec<_>>();
+
+		let query = PackageQuery::new().filter(Filter::Tag("graphics".into()));
+		assert_eq!(db.query(&query).len(), 2);
+
+		let query = query.filter(Filter::ReleaseStatus(ReleaseStatus::Testing).or(Filter::License("gpl-3.0".into())).not());
+		assert!(db.query(&query).is_empty());
+
+		let query = PackageQuery::new().latest_only().sort_by(SortBy::ReleaseDate, true);
+		let expected = vec![
+			db.get_from_identifier_and_version("Alpha", &PackageVersion::new("2.0").unwrap()).unwrap().identifier.to_string(),
+			db.get_from_identifier_and_version("Beta", &PackageVersion::new("1.0").unwrap()).unwrap().identifier.to_string(),
+			db.get_from_identifier_and_version("Delta", &PackageVersion::new("1.0").unwrap()).unwrap().identifier.to_string(),
+			db.get_from_identifier_and_version("Gamma", &PackageVersion::new("1.0").unwrap()).unwrap().identifier.to_string(),
+		];
+		assert_eq!(ids(db.query(&query)), expected);
+
+		let query = PackageQuery::new().filter(Filter::ReleasedAfter(Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap())).sort_by(SortBy::Name, false);
+		assert_eq!(db.query(&query).len(), 3);
+	}
+}
@@ -0,0 +1,75 @@
+//! Reporting of repository entries which couldn't be added to the MetaDB.
+
+use super::*;
+
+/// The step at which an entry was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RejectionStage {
+	/// The entry isn't valid JSON.
+	Json,
+	/// The entry doesn't match the `.ckan` schema.
+	Schema,
+	/// A [`Package`] couldn't be created from the JSON.
+	Package,
+}
+
+impl std::fmt::Display for RejectionStage {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Json => write!(f, "JSON parsing"),
+			Self::Schema => write!(f, "schema validation"),
+			Self::Package => write!(f, "package creation"),
+		}
+	}
+}
+
+/// A `.ckan` file from a repository which was skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedEntry {
+	/// The repository containing the entry.
+	pub repository: String,
+	/// The path of the entry within the repository.
+	pub path: String,
+	/// The identifier of the package if it could be read from the entry.
+	pub identifier: Option<String>,
+	pub stage: RejectionStage,
+	pub error: String,
+}
+
+impl std::fmt::Display for RejectedEntry {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "entry {} in repository {}", self.path, self.repository)?;
+		if let Some(identifier) = &self.identifier {
+			write!(f, " ({})", identifier)?;
+		}
+		write!(f, " failed {}: {}", self.stage, self.error)
+	}
+}
+
+/// Entries which were skipped while generating or updating a [`MetaDB`].
+#[derive(Debug, Default, Clone)]
+pub struct GenerationReport {
+	/// Sorted by repository then path.
+	pub rejected: Vec<RejectedEntry>,
+}
+
+impl GenerationReport {
+	/// Returns `true` if no entries were rejected.
+	pub fn is_empty(&self) -> bool {
+		self.rejected.is_empty()
+	}
+}
+
+impl MetaDB {
+	/// Gets every entry currently rejected from the DB's repositories.
+	///
+	/// This includes entries rejected by earlier updates which haven't changed since.
+	pub fn get_generation_report(&self) -> GenerationReport {
+		let mut rejected = self.repositories.values()
+			.flat_map(|info| info.rejected_entries())
+			.cloned()
+			.collect::<Vec<_>>();
+		rejected.sort_by(|a, b| a.repository.cmp(&b.repository).then_with(|| a.path.cmp(&b.path)));
+		GenerationReport { rejected }
+	}
+}
@@ -0,0 +1,123 @@
+//! Package repositories the MetaDB is generated from.
+
+use super::*;
+
+/// A source of `.ckan` packages.
+///
+/// When multiple repositories contain a package with the same [`PackageIdentifier`]
+/// the package from the repository with the highest `priority` is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repository {
+	/// Name used to track which packages came from this repository, must be unique.
+	pub name: String,
+	/// URL of a `.tar.gz` archive containing the repository.
+	pub url: String,
+	/// Higher priority repositories override packages from lower priority repositories.
+	pub priority: i32,
+	/// When set packages are read from this path instead of being downloaded from `url`.
+	///
+	/// See [`MetaDB::update_from_path()`] for the supported sources.
+	pub local_path: Option<std::path::PathBuf>,
+}
+
+impl Repository {
+	pub fn new(name: impl Into<String>, url: impl Into<String>, priority: i32) -> Self {
+		Self {
+			name: name.into(),
+			url: url.into(),
+			priority,
+			local_path: None,
+		}
+	}
+}
+
+impl Default for Repository {
+	/// The official CKAN-meta repository.
+	fn default() -> Self {
+		Self::new("CKAN-meta", "https://github.com/KSP-CKAN/CKAN-meta/archive/master.tar.gz", 0)
+	}
+}
+
+impl MetaDB {
+	/// Gets the name of the repository a package was taken from.
+	pub fn get_package_repository(&self, id: impl AsRef<PackageIdentifier>) -> Option<&str> {
+		self.origins.get(id.as_ref()).map(|s| s.as_str())
+	}
+
+	/// Adds a package which doesn't belong to any repository, replacing an earlier local package with the same identifier and version.
+	///
+	/// # Errors
+	/// [`AlreadyExists`](crate::Error::AlreadyExists) when a package from a repository has the same identifier and version.
+	pub fn put_local_package(&mut self, package: Package) -> crate::Result<()> {
+		if self.origins.contains_key(&package.identifier) {
+			return Err(crate::Error::AlreadyExists)
+		}
+		self.put_package(package);
+		Ok(())
+	}
+
+	/// Removes a package added with [`put_local_package()`](MetaDB::put_local_package()), packages from repositories are left alone.
+	pub fn take_local_package(&mut self, id: &PackageIdentifier) -> Option<Package> {
+		if self.origins.contains_key(id) {
+			return None
+		}
+		self.take_package(id)
+	}
+
+	/// Adds a package from `repository`, if the package already exists the one with the higher priority is kept.
+	pub(super) fn insert_package(&mut self, repository: &Repository, package: Package) {
+		let id = package.identifier.clone();
+
+		let existing_repository = match self.origins.get(&id) {
+			Some(r) => r.clone(),
+			None => {
+				self.origins.insert(id, repository.name.clone());
+				self.put_package(package);
+				return
+			},
+		};
+
+		if existing_repository == repository.name {
+			self.put_package(package);
+		} else if self.get_repository_priority(&existing_repository) >= repository.priority {
+			log::debug!("Package {} from repository {} is overridden by repository {}", id, repository.name, existing_repository);
+			self.shadowed.entry(id).or_default().push((repository.name.clone(), package));
+		} else {
+			log::debug!("Package {} from repository {} overrides repository {}", id, repository.name, existing_repository);
+			let existing = self.put_package(package).expect("package with an origin should be present.");
+			self.shadowed.entry(id.clone()).or_default().push((existing_repository, existing));
+			self.origins.insert(id, repository.name.clone());
+		}
+	}
+
+	/// Removes a package previously added from `repository`.
+	///
+	/// If the package was overriding a package from another repository that package takes its place.
+	pub(super) fn remove_package(&mut self, repository: &str, id: &PackageIdentifier) {
+		if self.origins.get(id).is_some_and(|r| r == repository) {
+			self.origins.remove(id);
+			self.take_package(id);
+
+			if let Some(mut shadowed) = self.shadowed.remove(id) {
+				let best = (0..shadowed.len()).max_by_key(|i| self.get_repository_priority(&shadowed[*i].0));
+				if let Some(best) = best {
+					let (name, package) = shadowed.swap_remove(best);
+					self.origins.insert(id.clone(), name);
+					self.put_package(package);
+				}
+				if !shadowed.is_empty() {
+					self.shadowed.insert(id.clone(), shadowed);
+				}
+			}
+		} else if let Some(shadowed) = self.shadowed.get_mut(id) {
+			shadowed.retain(|(name, _)| name != repository);
+			if shadowed.is_empty() {
+				self.shadowed.remove(id);
+			}
+		}
+	}
+
+	fn get_repository_priority(&self, name: &str) -> i32 {
+		self.repositories.get(name).map(|r| r.priority()).unwrap_or(i32::MIN)
+	}
+}
@@ -0,0 +1,120 @@
+//! Ranked text search over the packages in the MetaDB.
+
+use super::*;
+use package::KspVersionReal;
+
+/* Weights of each field, matching a whole word counts double. */
+const IDENTIFIER_WEIGHT: u32 = 12;
+const NAME_WEIGHT: u32 = 10;
+const TAGS_WEIGHT: u32 = 6;
+const AUTHOR_WEIGHT: u32 = 4;
+const BLURB_WEIGHT: u32 = 3;
+const DESCRIPTION_WEIGHT: u32 = 1;
+/// Added when the whole query is the identifier or name of a package.
+const EXACT_MATCH_BONUS: u32 = 100;
+
+/// A package found by [`MetaDB::search()`].
+#[derive(Debug, Clone, Copy)]
+pub struct SearchResult<'db> {
+	pub package: &'db Package,
+	/// Higher is a better match, only meaningful compared to other results of the same search.
+	pub score: u32,
+}
+
+/// Splits text into lowercase words.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+	text.split(|c: char| !c.is_alphanumeric())
+		.filter(|w| !w.is_empty())
+		.map(|w| w.to_lowercase())
+}
+
+/// Scores a single search term against the fields of a package, `0` if no field contains the term.
+fn score_term(package: &Package, term: &str) -> u32 {
+	let score_text = |text: &str, weight: u32| {
+		if words(text).any(|w| w == term) {
+			weight * 2
+		} else if text.to_lowercase().contains(term) {
+			weight
+		} else {
+			0
+		}
+	};
+
+	let mut score = score_text(package.identifier.identifier.as_str(), IDENTIFIER_WEIGHT) + score_text(package.name.as_str(), NAME_WEIGHT);
+	score += package.tags.iter().flatten().map(|t| score_text(t.as_str(), TAGS_WEIGHT)).max().unwrap_or(0);
+	score += package.author.iter().map(|a| score_text(a.as_str(), AUTHOR_WEIGHT)).max().unwrap_or(0);
+	score += score_text(package.blurb.as_str(), BLURB_WEIGHT);
+	score += package.description.as_deref().map(|d| score_text(d, DESCRIPTION_WEIGHT)).unwrap_or(0);
+	score
+}
+
+impl MetaDB {
+	/// Searches the `name`, `identifier`, `abstract`, `description`, `tags` and `author` of every package.
+	///
+	/// Every word in `query` must be found in at least one field of a package for it to match.
+	/// Only the latest version of each identifier is considered, when `compatible_ksp_versions` isn't empty
+	/// this is the latest version compatible with any of them.
+	///
+	/// Results are sorted from best to worst match. An empty query has no results.
+	pub fn search(&self, query: &str, compatible_ksp_versions: &[KspVersionReal]) -> Vec<SearchResult<'_>> {
+		let terms = words(query).collect::<Vec<_>>();
+		if terms.is_empty() {
+			return Vec::new()
+		}
+		let query = query.trim().to_lowercase();
+
+		let mut results = self.get_identifiers()
+			.filter_map(|identifier| {
+				self.get_packages_with_identifier(identifier).rev().find(|p| {
+					compatible_ksp_versions.is_empty() || compatible_ksp_versions.iter().any(|v| p.ksp_version.is_version_compatible(v, p.ksp_version_strict))
+				})
+			})
+			.filter_map(|package| {
+				let mut score = 0;
+				for term in &terms {
+					match score_term(package, term) {
+						0 => return None,
+						s => score += s,
+					}
+				}
+				if package.identifier.identifier.to_lowercase() == query || package.name.to_lowercase() == query {
+					score += EXACT_MATCH_BONUS;
+				}
+				Some(SearchResult { package, score })
+			})
+			.collect::<Vec<_>>();
+
+		results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.package.name.cmp(&b.package.name)));
+		results
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::package;
+	use serde_json::json;
+
+	#[test]
+	fn search_ranks_and_filters() {
+		let mut db = MetaDB::default();
+		db.put_package(package("Scatterer", "0.0.1", json!({ "name": "Scatterer", "abstract": "Atmospheric scattering.", "ksp_version": "1.11", "tags": ["graphics"] })));
+		db.put_package(package("Scatterer", "0.0.2", json!({ "name": "Scatterer", "abstract": "Atmospheric scattering.", "ksp_version": "1.12", "tags": ["graphics"] })));
+		db.put_package(package("EVE", "1.0", json!({ "name": "Environmental Visual Enhancements", "abstract": "Clouds and city lights, works well with Scatterer.", "ksp_version": "1.12", "tags": ["graphics"] })));
+		db.put_package(package("MechJeb2", "2.0", json!({ "name": "MechJeb 2", "abstract": "Autopilot.", "ksp_version": "1.12", "tags": ["graphics"] })));
+
+		let results = db.search("scatterer", &[]);
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].package.identifier.identifier, "Scatterer");
+		assert_eq!(results[0].package.identifier.version, PackageVersion::new("0.0.2").unwrap());
+		assert_eq!(results[1].package.identifier.identifier, "EVE");
+
+		let results = db.search("scatterer", &[KspVersionReal::new("1.11").unwrap()]);
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].package.identifier.version, PackageVersion::new("0.0.1").unwrap());
+
+		assert_eq!(db.search("graphics clouds", &[]).len(), 1);
+		assert!(db.search("graphics rockets", &[]).is_empty());
+		assert!(db.search("  ", &[]).is_empty());
+	}
+}
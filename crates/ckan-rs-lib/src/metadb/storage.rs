@@ -0,0 +1,305 @@
+//! On-disk format of the MetaDB.
+//!
+//! # Layout
+//! 1. A magic number identifying the file.
+//! 1. The format version as a little endian `u32`.
+//! 1. The length of the [`MetaDBHeader`] as a little endian `u64` followed by the header itself.
+//! 1. The DB itself, compressed as described by the header.
+//!
+//! Files written before the format was versioned have no magic number and are reported as version `0`.
+//!
+//! # Upgrades
+//! Versions listed in `UPGRADABLE_VERSIONS` are converted to the current layout when loaded.
+//! Any other version is reported as [`FormatStatus::NeedsRegeneration`] and the DB has to be generated again from the repositories.
+//!
+//! No older version is currently upgradable, every one of them needs regenerating:
+//! - Before `3` packages had fewer fields and untyped release dates and resources.
+//! - Version `3` didn't record the spec version or apply its defaults.
+//! - Version `4` stored download hashes as the bytes of their hex strings instead of typed digests.
+//!
+//! None of these can be filled in without the original metadata.
+
+use super::*;
+use std::io::{BufReader, BufWriter};
+use crate::Error::{Parse, NeedsRegeneration};
+
+const MAGIC: &[u8; 8] = b"CKANRSDB";
+
+/// The version of the on-disk format written by this library.
+///
+/// Bumped whenever the layout of the DB or its header changes.
+pub const FORMAT_VERSION: u32 = 5;
+
+/// Older format versions which can be converted to the current format when loaded, see the [module docs](self) for why this is empty.
+const UPGRADABLE_VERSIONS: &[u32] = &[];
+
+/// Compression applied to the DB when saved.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+	#[default]
+	None,
+	Gzip,
+}
+
+/// Whether a saved MetaDB can be loaded by this library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatStatus {
+	/// The file uses the current format.
+	Current,
+	/// The file uses an older format which is converted when loaded.
+	///
+	/// No older format is currently upgradable so this isn't returned, it's kept for future format changes which can be converted.
+	Upgradable(u32),
+	/// The file uses a format which can't be read and the DB must be regenerated.
+	NeedsRegeneration(u32),
+}
+
+impl FormatStatus {
+	fn of(version: u32) -> Self {
+		if version == FORMAT_VERSION {
+			Self::Current
+		} else if UPGRADABLE_VERSIONS.contains(&version) {
+			Self::Upgradable(version)
+		} else {
+			Self::NeedsRegeneration(version)
+		}
+	}
+}
+
+/// A repository the saved DB was generated from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositorySource {
+	pub name: String,
+	pub priority: i32,
+	/// SHA-256 digest of the archive the packages were read from, `None` for local directories.
+	pub archive_hash: Option<String>,
+}
+
+/// Describes a saved MetaDB, it can be read without loading the packages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaDBHeader {
+	generated: Option<std::time::SystemTime>,
+	repositories: Vec<RepositorySource>,
+	compression: Compression,
+}
+
+impl MetaDBHeader {
+	/// When the packages were last generated or updated.
+	pub fn generated(&self) -> Option<std::time::SystemTime> {
+		self.generated
+	}
+
+	/// The repositories the packages were taken from, highest priority first.
+	pub fn repositories(&self) -> &Vec<RepositorySource> {
+		&self.repositories
+	}
+
+	pub fn compression(&self) -> Compression {
+		self.compression
+	}
+}
+
+fn metadb_path(config: &crate::CkanRsConfig) -> std::path::PathBuf {
+	config.data_dir().join("metadb.bin")
+}
+
+/// Reads the magic number and format version.
+fn read_format_version(reader: &mut impl Read) -> crate::Result<u32> {
+	let mut magic = [0u8; 8];
+	reader.read_exact(&mut magic).map_err(|_| Parse("MetaDB file is truncated".to_string()))?;
+	if &magic != MAGIC {
+		return Ok(0)
+	}
+
+	let mut version = [0u8; 4];
+	reader.read_exact(&mut version).map_err(|_| Parse("MetaDB file is truncated".to_string()))?;
+	Ok(u32::from_le_bytes(version))
+}
+
+/// Describes `db` as it would be saved with `compression`.
+fn header_of(db: &MetaDB, compression: Compression) -> MetaDBHeader {
+	let mut repositories = db.repositories.iter()
+		.map(|(name, info)| RepositorySource { name: name.clone(), priority: info.priority(), archive_hash: info.hash().cloned() })
+		.collect::<Vec<_>>();
+	repositories.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
+
+	MetaDBHeader {
+		generated: db.generated,
+		repositories,
+		compression,
+	}
+}
+
+/// Checks `version` can be read at all.
+fn check_supported(version: u32) -> crate::Result<()> {
+	match FormatStatus::of(version) {
+		FormatStatus::NeedsRegeneration(v) => Err(NeedsRegeneration(format!("format version {} is not supported, the current version is {}", v, FORMAT_VERSION))),
+		_ => Ok(()),
+	}
+}
+
+/// Reads the header following the format version.
+///
+/// # Errors
+/// - [`NeedsRegeneration`](crate::Error::NeedsRegeneration) when `version` can't be read.
+/// - [`Parse`](crate::Error::Parse) when the header is corrupt.
+fn read_header(reader: &mut impl Read, version: u32) -> crate::Result<MetaDBHeader> {
+	check_supported(version)?;
+
+	let mut length = [0u8; 8];
+	reader.read_exact(&mut length).map_err(|_| Parse("MetaDB file is truncated".to_string()))?;
+	let mut header = vec![0u8; u64::from_le_bytes(length) as usize];
+	reader.read_exact(&mut header).map_err(|_| Parse("MetaDB file is truncated".to_string()))?;
+	bincode::deserialize::<MetaDBHeader>(&header).map_err(|_| Parse("MetaDB header is corrupt".to_string()))
+}
+
+/// Decodes the DB written with `version` of the format.
+///
+/// # Errors
+/// - [`NeedsRegeneration`](crate::Error::NeedsRegeneration) when `version` isn't upgradable or the old layout can't be read.
+/// - [`Parse`](crate::Error::Parse) when a current format DB is corrupt.
+fn decode_body(version: u32, body: &[u8]) -> crate::Result<MetaDB> {
+	match version {
+		FORMAT_VERSION => bincode::deserialize::<MetaDB>(body).map_err(|_| Parse("MetaDB is corrupt".to_string())),
+		/* Conversions from the versions in `UPGRADABLE_VERSIONS` go here. */
+		v => Err(NeedsRegeneration(format!("no upgrade from format version {}", v))),
+	}
+}
+
+impl MetaDB {
+	/// Checks if the saved MetaDB can be loaded without reading the whole file.
+	///
+	/// # Errors
+	/// - [`IO`](`crate::error::Error::IO`) when opening the file.
+	/// - [`Parse`](`crate::error::Error::Parse`) when the file is too short to contain a format version.
+	pub fn check_format(config: &crate::CkanRsConfig) -> crate::Result<FormatStatus> {
+		let mut f = BufReader::new(std::fs::File::open(metadb_path(config))?);
+		Ok(FormatStatus::of(read_format_version(&mut f)?))
+	}
+
+	/// Reads the header of the saved MetaDB without loading the packages.
+	///
+	/// # Errors
+	/// - [`IO`](`crate::error::Error::IO`) when opening the file.
+	/// - [`NeedsRegeneration`](`crate::error::Error::NeedsRegeneration`) when the file uses a format which can't be read.
+	/// - [`Parse`](`crate::error::Error::Parse`) when the header is corrupt.
+	pub fn read_header(config: &crate::CkanRsConfig) -> crate::Result<MetaDBHeader> {
+		let mut f = BufReader::new(std::fs::File::open(metadb_path(config))?);
+		let version = read_format_version(&mut f)?;
+		read_header(&mut f, version)
+	}
+
+	/// Loads the MetaDB along with its [`BuildCatalog`].
+	///
+	/// Only files using the current format can be loaded, older files have to be regenerated.
+	///
+	/// A missing build catalog is treated as empty.
+	///
+	/// # Errors
+	/// - [`IO`](`crate::error::Error::IO`) when opening or reading from the file.
+	/// - [`NeedsRegeneration`](`crate::error::Error::NeedsRegeneration`) when the file uses a format which can't be read, see [`check_format()`](MetaDB::check_format()).
+	/// - [`Parse`](`crate::error::Error::Parse`) when the file is corrupt.
+	pub fn load_from_disk(config: &crate::CkanRsConfig) -> crate::Result<MetaDB> {
+		let mut f = BufReader::new(std::fs::File::open(metadb_path(config))?);
+		let version = read_format_version(&mut f)?;
+
+		let header = read_header(&mut f, version)?;
+
+		let mut v = Vec::<u8>::new();
+		match header.compression {
+			Compression::None => f.read_to_end(&mut v)?,
+			Compression::Gzip => flate2::bufread::GzDecoder::new(f).read_to_end(&mut v).map_err(|_| Parse("MetaDB is corrupt".to_string()))?,
+		};
+
+		let mut db = decode_body(version, &v)?;
+		if version != FORMAT_VERSION {
+			log::info!("Upgraded MetaDB from format version {} to {}", version, FORMAT_VERSION);
+		}
+		db.rebuild_indexes();
+
+		db.builds = match BuildCatalog::load_from_disk(config) {
+			Ok(builds) => builds,
+			Err(crate::Error::IO(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+				log::warn!("Build catalog not found, game versions will be unavailable until the MetaDB is updated.");
+				Default::default()
+			},
+			Err(e) => return Err(e),
+		};
+		Ok(db)
+	}
+
+	/// Saves the MetaDB along with its [`BuildCatalog`].
+	///
+	/// The DB is compressed according to [`metadb_compression()`](crate::CkanRsConfig::metadb_compression()).
+	///
+	/// # Errors
+	/// - [`IO`](`crate::error::Error::IO`) when creating or writing to the file.
+	/// - [`Parse`](`crate::error::Error::Parse`) when serializing the file.
+	pub fn save_to_disk(&self, config: &crate::CkanRsConfig) -> crate::Result<()> {
+		let header = header_of(self, config.metadb_compression());
+		let header_data = bincode::serialize(&header).map_err(|_| Parse("Serialize failed".to_string()))?;
+		let data = bincode::serialize(self).map_err(|_| Parse("Serialize failed".to_string()))?;
+
+		let mut f = BufWriter::new(std::fs::File::create(metadb_path(config))?);
+		f.write_all(MAGIC)?;
+		f.write_all(&FORMAT_VERSION.to_le_bytes())?;
+		f.write_all(&(header_data.len() as u64).to_le_bytes())?;
+		f.write_all(&header_data)?;
+		match header.compression {
+			Compression::None => f.write_all(&data)?,
+			Compression::Gzip => {
+				let mut gz = flate2::write::GzEncoder::new(&mut f, flate2::Compression::default());
+				gz.write_all(&data)?;
+				gz.finish()?;
+			},
+		}
+		f.flush()?;
+
+		self.builds.save_to_disk(config)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn save_and_load_formats() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut config = crate::CkanRsConfig::default();
+		assert!(config.set_data_dir(dir.path().to_path_buf()));
+		config.set_metadb_compression(Compression::Gzip);
+
+		let (db, _) = MetaDB::generate_from_path(dir.path(), false).unwrap();
+		db.save_to_disk(&config).unwrap();
+		assert_eq!(MetaDB::check_format(&config).unwrap(), FormatStatus::Current);
+
+		let header = MetaDB::read_header(&config).unwrap();
+		assert_eq!(header.compression(), Compression::Gzip);
+		assert!(header.generated().is_some());
+		assert_eq!(header.repositories().len(), 1);
+		MetaDB::load_from_disk(&config).unwrap();
+
+		/* Corrupting the body is a parse error rather than a format change */
+		let mut data = std::fs::read(metadb_path(&config)).unwrap();
+		let len = data.len();
+		data.truncate(len - 4);
+		std::fs::write(metadb_path(&config), &data).unwrap();
+		assert!(matches!(MetaDB::load_from_disk(&config), Err(Parse(_))));
+
+		/* Files from before the format was versioned need regenerating */
+		std::fs::write(metadb_path(&config), b"unversioned data").unwrap();
+		assert_eq!(MetaDB::check_format(&config).unwrap(), FormatStatus::NeedsRegeneration(0));
+		assert!(matches!(MetaDB::load_from_disk(&config), Err(NeedsRegeneration(_))));
+
+		/* As do every older and unknown version */
+		for version in (1..FORMAT_VERSION).chain([FORMAT_VERSION + 1]) {
+			let mut data = MAGIC.to_vec();
+			data.extend_from_slice(&version.to_le_bytes());
+			std::fs::write(metadb_path(&config), &data).unwrap();
+			assert_eq!(MetaDB::check_format(&config).unwrap(), FormatStatus::NeedsRegeneration(version));
+			assert!(matches!(MetaDB::load_from_disk(&config), Err(NeedsRegeneration(_))));
+		}
+	}
+}
@@ -5,7 +5,7 @@
 //! 1. Use the builder to add package requirements or game versions.
 //! 1. [`ResolverBuilder::build()`] to get a [`ResolverProcessor`]
 //! 1. [`ResolverProcessor::attempt_resolve()`] until complete while answering any decisions presented
-//! by [`ResolverStatus::DecisionsRequired`] by calling [`ResolverProcessor::add_decision()`] with a choice from the decisions vector.
+//!    by [`ResolverStatus::DecisionsRequired`] by calling [`ResolverProcessor::add_decision()`] with a choice from the decisions vector.
 //! 1. [`ResolverProcessor::finalize()`] to get a [`ResolverFinalized`] to query.
 //! 1. [`ResolverFinalized::get_new_packages()`] to list all new packages to be installed.
 
@@ -142,7 +142,7 @@ impl DependencyGraph {
 	fn add_node_edges_from_package(&mut self, package: &package::Package, src: NodeIndex) {
 		for req in &package.depends {
 			match req {
-				Relationship::AnyOf(r) => {
+				Relationship::AnyOf { any_of: r, .. } => {
 					let decision = self.graph.add_node(NodeData::Decision);
 					self.graph.add_edge(src, decision, EdgeData::Decision);
 					for b_desc in r {
@@ -151,7 +151,7 @@ impl DependencyGraph {
 						self.graph.add_edge(decision, b, EdgeData::AnyOf(b_desc.version.clone()));
 					}
 				},
-				Relationship::One(b_desc) => {
+				Relationship::One { descriptor: b_desc, .. } => {
 					let b = self.get_or_add_node_index(&b_desc.name);
 					if let NodeData::Candidate(_, data) = &mut self.graph[b] { data.dirty = true; }
 					self.graph.add_edge(src, b, EdgeData::Depends(b_desc.version.clone()));
@@ -259,7 +259,7 @@ impl PackageTree<InProgress> {
 			let bounds = self.dep_graph.get_version_bounds_for_node(src).ok_or(DeterminePackageError::VersionBoundsImcompatible)?;
 
 			/* We don't use `bounds` yet, we want to grab every package providing the identifier so we can tell if it exists at all */
-			let matching_packages_providing = metadb.get_packages().iter().get_packages_providing(&PackageDescriptor { name: name.clone(), version: VersionBounds::Any });
+			let matching_packages_providing = metadb.get_packages_providing(&PackageDescriptor { name: name.clone(), version: VersionBounds::Any });
 			if matching_packages_providing.is_empty() {
 				Err(DeterminePackageError::IdentifierDoesNotExist)
 			} else if matching_packages_providing.len() == 1 {
@@ -276,8 +276,7 @@ impl PackageTree<InProgress> {
 				So we don't need to track the attempted packages or iterate all possible candidates.
 				*/
 
-				m.sort();
-				let latest = m[0];
+				let latest = *m.iter().max().expect("m should not be empty.");
 
 				self.dep_graph.set_node_as_package(src, latest);
 				
@@ -350,7 +349,7 @@ impl PackageTree<Complete> {
 			self.dep_graph.graph.add_edge(self.dep_graph.meta_node, new, EdgeData::Depends(target.required_version.clone()));
 		}
 
-		for target in remove {
+		for _target in remove {
 			todo!();
 		}
 
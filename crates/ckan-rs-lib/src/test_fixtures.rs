@@ -0,0 +1,117 @@
+//! Packages and archives shared by the unit tests.
+
+use crate::metadb::package::{Package, PackageIdentifier, PackageVersion};
+
+/// Metadata of a minimal valid package.
+///
+/// Fields in `extra` are added or replace the defaults, `null` fields are removed.
+pub fn ckan_json(identifier: &str, version: &str, extra: serde_json::Value) -> serde_json::Value {
+	let mut json = serde_json::json!({
+		"spec_version": 1,
+		"identifier": identifier,
+		"version": version,
+		"name": identifier,
+		"abstract": "A test package.",
+		"author": "tester",
+		"license": "MIT",
+		"download": format!("https://example.com/{identifier}.zip"),
+	});
+	let fields = json.as_object_mut().expect("package metadata should be an object.");
+	for (k, v) in extra.as_object().expect("extra fields should be an object.") {
+		if v.is_null() {
+			fields.remove(k);
+		} else {
+			fields.insert(k.clone(), v.clone());
+		}
+	}
+	json
+}
+
+/// Reads the metadata from [`ckan_json()`] as a package.
+pub fn package(identifier: &str, version: &str, extra: serde_json::Value) -> Package {
+	Package::read_from_json(ckan_json(identifier, version, extra)).expect("test package should be valid.")
+}
+
+pub fn id(identifier: &str, version: &str) -> PackageIdentifier {
+	PackageIdentifier { identifier: identifier.to_string(), version: PackageVersion::new(version).expect("test version should be valid.") }
+}
+
+/// Builds an uncompressed tar archive of `entries` laid out like a repository, a `builds.json` is always included.
+pub fn build_archive(entries: &[(&str, String)]) -> Vec<u8> {
+	let mut builder = tar::Builder::new(Vec::<u8>::new());
+	for (path, data) in entries.iter().chain(std::iter::once(&("CKAN-meta-master/builds.json", r#"{"builds": {"3190": "1.12.5.3190"}}"#.to_string()))) {
+		let mut header = tar::Header::new_gnu();
+		header.set_size(data.len() as u64);
+		header.set_mode(0o644);
+		header.set_cksum();
+		builder.append_data(&mut header, path, data.as_bytes()).expect("failed to append archive entry.");
+	}
+	builder.into_inner().expect("failed to finish archive.")
+}
+
+/// A request received by [`serve()`].
+#[derive(Debug, Clone)]
+pub struct Request {
+	pub path: String,
+	/// Header names are lowercase.
+	pub headers: std::collections::HashMap<String, String>,
+}
+
+/// Serves HTTP on a free port of `address` for the rest of the test.
+///
+/// `respond` is given each request and the connection to write the response to, see [`response()`].
+pub async fn serve<F, Fut>(address: &str, respond: F) -> std::net::SocketAddr
+where
+	F: Fn(Request, tokio::net::TcpStream) -> Fut + Send + Sync + 'static,
+	Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+	let listener = tokio::net::TcpListener::bind((address, 0)).await.expect("failed to bind test server.");
+	let local_address = listener.local_addr().expect("test server should have an address.");
+	let respond = std::sync::Arc::new(respond);
+	tokio::spawn(async move {
+		while let Ok((mut stream, _)) = listener.accept().await {
+			let respond = respond.clone();
+			tokio::spawn(async move {
+				if let Some(request) = read_request(&mut stream).await {
+					respond(request, stream).await;
+				}
+			});
+		}
+	});
+	local_address
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<Request> {
+	use tokio::io::AsyncReadExt;
+
+	let mut data = Vec::new();
+	let mut buf = [0u8; 1024];
+	while !data.windows(4).any(|w| w == b"\r\n\r\n") {
+		let read = stream.read(&mut buf).await.ok()?;
+		if read == 0 {
+			return None
+		}
+		data.extend_from_slice(&buf[..read]);
+	}
+
+	let text = String::from_utf8_lossy(&data);
+	let mut lines = text.split("\r\n");
+	let path = lines.next()?.split(' ').nth(1)?.to_string();
+	let headers = lines
+		.filter_map(|line| line.split_once(':'))
+		.map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+		.collect();
+	Some(Request { path, headers })
+}
+
+/// Builds a response which closes the connection, `Content-Length` is only sent if it's in `headers`.
+pub fn response(status: &str, headers: &[(&str, String)], body: &[u8]) -> Vec<u8> {
+	let mut data = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+	for (name, value) in headers {
+		data += &format!("{name}: {value}\r\n");
+	}
+	data += "\r\n";
+	let mut data = data.into_bytes();
+	data.extend_from_slice(body);
+	data
+}
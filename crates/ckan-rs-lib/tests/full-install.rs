@@ -1,18 +1,18 @@
 #[tokio::test]
 async fn full_install() {
 	use ckan_rs::game_instance::GameInstance;
-	use ckan_rs::relationship_resolver::*;
+	use ckan_rs::relationship_resolver::InstallTarget;
 	use ckan_rs::metadb::package::*;
 
 	env_logger::builder().is_test(true).try_init().expect("failed to create logger.");
 
 	let config = ckan_rs::CkanRsConfig::default();
 	
-	let db = {
+	let mut db = {
 		if let Ok(db) = ckan_rs::MetaDB::load_from_disk(&config) {
 			db
 		} else {
-			let db = ckan_rs::metadb::generate_latest().await.expect("failed to generate metadb.");
+			let (db, _) = ckan_rs::metadb::generate_latest(&config).await.expect("failed to generate metadb.");
 			db.save_to_disk(&config).expect("failed to save metadb.");
 			db
 		}
@@ -28,36 +28,6 @@ async fn full_install() {
 	];
 
 
-	let mut resolver = ResolverBuilder::new(&db)
-		.compatible_ksp_versions(compatible_ksp_versions.clone())
-		.add_package_requirements(requirements)
-		.build();
-		
-	let packages = loop {
-		match resolver.attempt_resolve() {
-			ResolverStatus::Complete => {
-				break resolver.finalize().expect("resolve not complete.").get_new_packages();
-			},
-			ResolverStatus::DecisionsRequired(infos) => {
-				for info in infos {
-					let mut options = info.options.clone();
-					options.sort(); /* We want to always choses the same option */
-					log::info!("choosing `{}` from options {:?} required by `{}`", &info.options[0], &info.options, info.source);
-					resolver.add_decision(&info.options[0]);
-				}
-			},
-			ResolverStatus::Failed(fails) => {
-				log::error!("RESOLVER FAILS DUMP\n{:?}", fails);
-				panic!("resolver failed."); 
-			},
-		}
-	};
-
-	log::info!("Final Package List:");
-	for package in &packages {
-		log::info!("\tID: {} VERSION: {:?}", package.identifier, package.version);
-	}
-
 	let mut instance = {
 		let instance_path = ckan_rs_test_utils::create_fake_game_instance().expect("failed to create test game instance.");
 		GameInstance::new(&config, db.get_game_builds(), "test".into(), &instance_path, instance_path.parent().unwrap().join("deployment")).unwrap()
@@ -65,6 +35,20 @@ async fn full_install() {
 	
 	instance.set_compatible_ksp_versions(compatible_ksp_versions);
 
+	let (packages, _) = instance.alter_package_requirements(&mut db, requirements, vec![], |tree, infos| {
+		for info in infos {
+			let mut options = info.options.clone();
+			options.sort(); /* We want to always choses the same option */
+			log::info!("choosing `{}` from options {:?} required by `{}`", &options[0], &info.options, info.source);
+			tree.add_decision(&options[0]);
+		}
+	}).expect("resolver failed.");
+
+	log::info!("Final Package List:");
+	for package in &packages {
+		log::info!("\tID: {} VERSION: {:?}", package.identifier, package.version);
+	}
+
 	let packages = packages.iter()
 		.map(|m| db.get_from_unique_id(m).expect("metadb package not found"))
 		.collect::<Vec<_>>();
@@ -78,7 +62,6 @@ async fn full_install() {
 
 	for package in packages {
 		ckan_rs::installation::content::extract_content_to_deployment(&config, &instance, package).unwrap();
-		instance.enable_package(package);
 	}
 
 	instance.redeploy_packages(&db).await.expect("deployment failed");
@@ -9,7 +9,7 @@ async fn resolve_dependency() {
 		if let Ok(db) = ckan_rs::MetaDB::load_from_disk(&config) {
 			db
 		} else {
-			let db = ckan_rs::metadb::generate_latest().await.expect("failed to generate metadb.");
+			let (db, _) = ckan_rs::metadb::generate_latest(&config).await.expect("failed to generate metadb.");
 			db.save_to_disk(&config).expect("failed to save metadb.");
 			db
 		}
@@ -24,15 +24,13 @@ async fn resolve_dependency() {
 		// InstallRequirement {identifier: "Parallax".to_string(), ..Default::default() },
 	];
 
-	let mut resolver = ResolverBuilder::new(&db)
-		.add_package_requirements(requirements)
-		.compatible_ksp_versions(compatible_ksp_versions)
-		.build();
+	let mut resolver = PackageTree::<Complete>::new(compatible_ksp_versions)
+		.alter_package_requirements(requirements, vec![]);
 
 	loop {
-		match resolver.attempt_resolve() {
+		match resolver.attempt_resolve(&db) {
 			ResolverStatus::Complete => {
-				let packages = resolver.finalize().expect("resolver complete status but not complete flagged").get_new_packages();
+				let packages = resolver.complete().expect("resolver complete status but not complete flagged").get_all_packages();
 
 				/* XXX: Potential false positive if the dependency list of these mods changes. */
 				let expected = [
@@ -35,12 +35,18 @@ async fn main() {
 	});
 
 	async fn genreate_and_save_new_metadb(config: &ckan_rs::CkanRsConfig) -> ckan_rs::Result<ckan_rs::MetaDB> {
-		let db = ckan_rs::metadb::generate_latest().await?;
+		let (db, report) = ckan_rs::metadb::generate_latest(config).await?;
+		for rejected in &report.rejected {
+			log::debug!("Rejected {}", rejected);
+		}
+		if !report.is_empty() {
+			log::warn!("{} metadb entries were rejected.", report.rejected.len());
+		}
 		db.save_to_disk(config)?;
 		Ok(db)
 	}
 
-	let db = match ckan_rs::MetaDB::load_from_disk(&config) {
+	let mut db = match ckan_rs::MetaDB::load_from_disk(&config) {
 		Ok(db) => db,
 		Err(e) => {
 			match e {
@@ -62,8 +68,19 @@ async fn main() {
 						}
 					}
 				},
+				ckan_rs::Error::NeedsRegeneration(e) => {
+					log::info!("MetaDB format changed ({}). regenerating...", e);
+					let res = genreate_and_save_new_metadb(&config).await;
+					match res {
+						Ok(db) => db, 
+						Err(_) => {
+							log::error!("Failed to generate metadb");
+							return
+						}
+					}
+				},
 				ckan_rs::Error::Parse(_) => {
-					log::warn!("Failed to open MetaDB due to parsing error, DB format likely changed. regenerating...");
+					log::warn!("Failed to open MetaDB due to parsing error, DB is likely corrupt. regenerating...");
 					let res = genreate_and_save_new_metadb(&config).await;
 					match res {
 						Ok(db) => db, 
@@ -83,7 +100,7 @@ async fn main() {
 		return;
 	}
 
-	if parsed_options.free.get(0).unwrap() == "instance" {
+	if parsed_options.free.first().unwrap() == "instance" {
 		if parsed_options.free.get(1).unwrap() == "create" {
 			let name = match parsed_options.free.get(2) {
 				Some(p) => p,
@@ -99,8 +116,24 @@ async fn main() {
 				Ok(_) => {},
 				Err(e) => log::info!("Failed to create instance due to error: {:?}", e),
 			}
+		} else if parsed_options.free.get(1).unwrap() == "add-local" {
+			let name = match parsed_options.free.get(2) {
+				Some(p) => p,
+				None => { log::error!("Instance name not provided."); return },
+			};
+
+			let ckan_path = match parsed_options.free.get(3) {
+				Some(p) => std::path::PathBuf::from(p),
+				None => { log::error!(".ckan file path not provided."); return },
+			};
+			let archive_path = parsed_options.free.get(4).map(std::path::PathBuf::from);
+
+			match add_local_package(&config, &mut db, name, ckan_path, archive_path) {
+				Ok(_) => {},
+				Err(e) => log::info!("Failed to add local package due to error: {:?}", e),
+			}
 		}
-	} else if parsed_options.free.get(0).unwrap() == "install" {
+	} else if parsed_options.free.first().unwrap() == "install" {
 		let name = match parsed_options.free.get(1) {
 			Some(p) => p,
 			None => { log::error!("Instance name not provided."); return },
@@ -108,11 +141,42 @@ async fn main() {
 
 		let package_names = &parsed_options.free[2..];
 
-		match install_packages(&config, &db, name, package_names).await {
+		match install_packages(&config, &mut db, name, package_names).await {
 			Ok(_) => {},
 			Err(e) => log::info!("Failed to install packages due to error: {:?}", e),
 		}
+	} else if parsed_options.free.first().unwrap() == "cache" {
+		let result = match parsed_options.free.get(1).map(String::as_str) {
+			Some("verify") => verify_cache(&config, &db).await,
+			Some("list") => list_cache(&config, &db),
+			Some("evict") => ckan_rs::installation::cache::evict_download_cache(&config).map(|removed| println!("Evicted {} archives.", removed.len())).map_err(Error::from),
+			Some("purge") => ckan_rs::installation::cache::purge_download_cache(&config).map(|removed| println!("Removed {} files.", removed.len())).map_err(Error::from),
+			Some("purge-unused") => ckan_rs::installation::cache::purge_unused_downloads(&config).map(|removed| println!("Removed {} archives.", removed.len())).map_err(Error::from),
+			_ => { log::error!("Unknown cache command."); return },
+		};
+		if let Err(e) = result {
+			log::info!("Failed to manage download cache due to error: {:?}", e);
+		}
+	}
+}
+
+fn list_cache(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB) -> Result<(), Error> {
+	let cached = ckan_rs::installation::cache::list_download_cache(config, db)?;
+	for archive in &cached {
+		let name = archive.package.as_ref().map_or_else(|| "unknown".to_string(), |id| id.to_string());
+		println!("{} {} bytes {}", name, archive.size, archive.path.display());
+	}
+	println!("{} archives, {} bytes.", cached.len(), cached.iter().map(|a| a.size).sum::<u64>());
+	Ok(())
+}
+
+async fn verify_cache(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB) -> Result<(), Error> {
+	let verification = ckan_rs::installation::cache::verify_download_cache(config, db, true).await?;
+	for (path, e) in &verification.invalid {
+		println!("Removed {}: {}", path.display(), e);
 	}
+	println!("{} valid, {} invalid, {} unknown.", verification.valid.len(), verification.invalid.len(), verification.unknown.len());
+	Ok(())
 }
 
 fn create_instance(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB, instance_path: impl AsRef<std::path::Path>, name: impl AsRef<str>) -> Result<(), Error> {
@@ -152,8 +216,19 @@ fn create_instance(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB, instanc
 	Ok(())
 }
 
-async fn install_packages(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB, instance_name: impl AsRef<str>, package_names: impl IntoIterator<Item = impl AsRef<str>>) -> Result<(), Error> {
+fn add_local_package(config: &ckan_rs::CkanRsConfig, db: &mut ckan_rs::MetaDB, instance_name: impl AsRef<str>, ckan_path: impl AsRef<std::path::Path>, archive_path: Option<std::path::PathBuf>) -> Result<(), Error> {
 	let mut instance = ckan_rs::game_instance::GameInstance::load_by_name(config, instance_name)?;
+	instance.register_local_packages(config, db);
+	instance.add_local_package_from_file(config, db, ckan_path, archive_path.as_deref())?;
+	instance.save_to_disk(config)?;
+	log::info!("Added local package succesfully.");
+	Ok(())
+}
+
+async fn install_packages(config: &ckan_rs::CkanRsConfig, db: &mut ckan_rs::MetaDB, instance_name: impl AsRef<str>, package_names: impl IntoIterator<Item = impl AsRef<str>>) -> Result<(), Error> {
+	let mut instance = ckan_rs::game_instance::GameInstance::load_by_name(config, instance_name)?;
+	/* Local packages are resolved without this but their archives may need copying back into the download cache */
+	instance.register_local_packages(config, db);
 
 	use ckan_rs::relationship_resolver::*;
 
@@ -172,7 +247,7 @@ async fn install_packages(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB,
 			loop {
 				let mut input = String::new();
 				if stdin.read_line(&mut input).is_ok() {
-					if let Ok(ans) = input.parse::<usize>() {
+					if let Ok(ans) = input.trim_end().parse::<usize>() {
 						if ans < options.len() {
 							tree.add_decision(&info.options[ans]);
 							break;
@@ -231,6 +306,8 @@ async fn install_packages(config: &ckan_rs::CkanRsConfig, db: &ckan_rs::MetaDB,
 	instance.redeploy_packages(db).await.map_err(|_| Error::Deployment)?;
 	instance.save_to_disk(config)?;
 
+	ckan_rs::installation::cache::evict_download_cache(config)?;
+
 	Ok(())
 }
 